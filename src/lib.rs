@@ -16,342 +16,561 @@
 //  limitations under the License.
 //////////////////////////////////////////////////////////////////////////////
 
-#![feature(plugin_registrar, rustc_private)]
+use std::hash::{DefaultHasher, Hash, Hasher};
 
-#[macro_use]
-extern crate syntax;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
 
-#[macro_use]
-extern crate rustc;
+use syn::{braced, bracketed, parenthesized, parse_quote, token};
+use syn::{AngleBracketedGenericArguments, Attribute, Block, GenericParam, Generics, Ident, Path, Token, Type, TypeParamBound, Visibility, WherePredicate};
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
 
-#[macro_use]
-extern crate rustc_plugin;
+use system::*;
 
-#[macro_use]
-extern crate lazy_static;
+mod system;
+mod util;
 
-use std::ops::Deref;
-use std::sync::Mutex;
-use std::collections::HashMap;
+const AUTO_TRAITS: &[&str] = &["Send", "Sync", "Unpin", "UnwindSafe", "RefUnwindSafe"];
 
-use rustc_plugin::Registry;
+mod kw {
+    syn::custom_keyword!(queue);
+    syn::custom_keyword!(clone);
+    syn::custom_keyword!(par);
+}
 
-use syntax::parse::parser::Parser;
-use syntax::ext::base::SyntaxExtension::IdentTT;
-use syntax::ext::base::{ExtCtxt, MacResult, DummyResult};
-use syntax::codemap::Span;
-use syntax::parse::token::{intern, Eof, Token, BinOpToken, DelimToken};
-use syntax::ast::*;
+#[proc_macro]
+pub fn handlers_define_system(tts: TokenStream) -> TokenStream {
+    let source = TokenStream2::from(tts.clone());
 
-use system::*;
-
-mod system;
-mod util;
+    let system = match define_system_macro.parse(tts) {
+        Ok(system) => system,
+        Err(err) => return err.to_compile_error().into()
+    };
 
-lazy_static! {
-    pub static ref DEFINED_SYSTEMS: Mutex<HashMap<String, SystemInfo>> = Mutex::new(HashMap::new());
+    let mut result = system.generate_ast();
+    let id = system_id(&source, &system.name);
+    result.extend(system.generate_export(source, id));
+    result.into()
 }
 
-#[plugin_registrar]
-pub fn plugin_registrar(reg: &mut Registry) {
-    reg.register_syntax_extension(intern("handlers_define_system"), IdentTT(Box::new(define_system_macro), None, false));
+#[proc_macro]
+pub fn handlers_impl_object(tts: TokenStream) -> TokenStream {
+    // Objects are implemented in two passes. The first asks the system's exported macro for
+    // the system's definition, which calls back into this macro with the definition prepended.
+    let result = if tts.clone().into_iter().next().is_some_and(|tt| tt.to_string() == "@") {
+        impl_object_macro.parse(tts)
+    } else {
+        let source = TokenStream2::from(tts.clone());
+        (|parser: ParseStream| lookup_system_macro(parser, source)).parse(tts)
+    };
 
-    reg.register_syntax_extension(intern("handlers_impl_object"), IdentTT(Box::new(impl_object_macro), None, false));
+    match result {
+        Ok(result) => result.into(),
+        Err(err) => err.to_compile_error().into()
+    }
 }
 
-fn define_system_macro<'a>(ctx: &'a mut ExtCtxt, macro_span: Span, ident: Ident, tts: Vec<TokenTree>) -> Box<MacResult + 'a> {
-    let name = ident.name.as_str().deref().to_owned();
+// Every system's exported macro is placed at the crate root, so each is given a hash of the
+// system's definition and where it was defined, to keep systems with the same name in different
+// modules apart. Only the alias in the system's module is ever named. Definitions expanded from
+// the same `macro_rules!` share a location, but the span's debug output includes its expansion.
+fn system_id(source: &TokenStream2, name: &Ident) -> u64 {
+    let span = name.span().unwrap();
+    let mut hasher = DefaultHasher::new();
+
+    source.to_string().hash(&mut hasher);
+    name.to_string().hash(&mut hasher);
+    (span.file(), span.line(), span.column()).hash(&mut hasher);
+    format!("{:?}", span).hash(&mut hasher);
+    hasher.finish()
+}
 
-    let mut systems = DEFINED_SYSTEMS.lock().unwrap();
-    if let Some(ref system) = systems.get(&name) {
-        ctx.struct_span_err(macro_span, &format!("Redefinition of system '{}'", name))
-            .span_note(system.span, "Previous definition was at:")
-            .emit();
+fn define_system_macro(parser: ParseStream) -> syn::Result<SystemInfo> {
+    let attrs = parser.call(Attribute::parse_outer)?;
+    let vis: Visibility = parser.parse()?;
+    let mut system = SystemInfo::new(vis, parser.parse()?);
+    system.set_attrs(attrs);
 
-        return DummyResult::any(macro_span);
-    }
+    let mut generics: Generics = parser.parse()?;
 
-    let mut system = SystemInfo::new(ident, macro_span);
-    let mut parser = ctx.new_parser_from_tts(&tts);
+    // Bounds such as `[Send + Sync]` are required of every object in the system
+    if parser.peek(token::Bracket) {
+        let content;
+        bracketed!(content in parser);
 
-    if parser.check(&Eof) {
-        ctx.span_err(macro_span, "Expected list of handler definitions");
-        return DummyResult::any(macro_span);
-    }
+        let bounds = Punctuated::<TypeParamBound, Token![+]>::parse_separated_nonempty(&content)?;
+
+        // Objects are stored as `dyn` trait objects, which can only add auto traits to the object trait
+        for bound in bounds.iter() {
+            let name = match *bound {
+                TypeParamBound::Trait(ref bound) => bound.path.segments.last().map(|segment| segment.ident.to_string()),
+                TypeParamBound::Lifetime(_) => continue,
+                _ => None
+            };
 
-    if parser.check(&Token::BinOp(BinOpToken::Star)) {
-        parser.expect(&Token::BinOp(BinOpToken::Star)).unwrap();
+            if !name.as_ref().is_some_and(|name| AUTO_TRAITS.contains(&name.as_str())) {
+                let message = match name {
+                    Some(name) => format!("Object bounds can only be auto traits such as Send or Sync, '{}' can be required of every object with '*: {}'", name, name),
+                    None => "Object bounds can only be auto traits such as Send or Sync".to_string()
+                };
 
-        if let Err(mut err) = parser.expect(&Token::Colon) {
-            err.emit();
-            return DummyResult::any(macro_span);
+                return Err(syn::Error::new_spanned(bound, message));
+            }
         }
 
+        system.set_object_bounds(bounds.into_iter().collect());
+    }
+
+    generics.where_clause = parser.parse()?;
+
+    // Lifetimes have to be declared before any other parameters
+    let (lifetimes, params): (Vec<GenericParam>, Vec<GenericParam>) = generics.params.into_iter()
+        .partition(|param| matches!(*param, GenericParam::Lifetime(_)));
+    generics.params = lifetimes.into_iter().chain(params).collect();
+
+    system.set_generics(generics);
+
+    let content;
+    let brace = braced!(content in parser);
+
+    if content.is_empty() {
+        return Err(syn::Error::new(brace.span.join(), "Expected list of handler definitions"));
+    }
+
+    if content.peek(Token![*]) {
+        content.parse::<Token![*]>()?;
+        content.parse::<Token![:]>()?;
+
         loop {
-            if parser.check(&Token::Semi) {
-                parser.expect(&Token::Semi).unwrap();
+            if content.peek(Token![;]) {
+                content.parse::<Token![;]>()?;
                 break;
             }
 
-            match parser.parse_ident() {
-                Ok(ident) => system.add_requirement(ident),
-                Err(mut err) => {
-                    err.emit();
-                    return DummyResult::any(macro_span);
-                }
-            };
+            system.add_requirement(content.parse()?);
 
-            if !parser.check(&Token::Comma) {
-                if parser.check(&Token::Semi) {
-                    parser.expect(&Token::Semi).unwrap();
+            if !content.peek(Token![,]) {
+                if content.peek(Token![;]) {
+                    content.parse::<Token![;]>()?;
                 }
 
                 break;
             }
 
-            parser.expect(&Token::Comma).unwrap();
+            content.parse::<Token![,]>()?;
         }
     }
 
     loop {
-        match parse_handler_definition(ctx, &mut parser) {
-            Some(handler) => system.add_handler(handler),
-            None => break
-        }
+        let handler = parse_handler_definition(&content, &system)?;
+        system.add_handler(handler);
 
-        if parser.check(&Eof) {
+        if content.is_empty() {
             break
         }
     }
 
-    let result = system.generate_ast();
-    systems.insert(name, system);
-    result
+    Ok(system)
 }
 
-fn impl_object_macro<'a>(ctx: &'a mut ExtCtxt, macro_span: Span, ident: Ident, tts: Vec<TokenTree>) -> Box<MacResult + 'a> {
-    let name = ident.name.as_str().deref().to_owned();
+// Splits a path to a system into the path to its module, including the trailing `::`, which
+// the system's items are named relative to, and the system's name
+fn parse_system_path(parser: ParseStream) -> syn::Result<(TokenStream2, Ident)> {
+    let mut path = Path::parse_mod_style(parser)?;
 
-    let systems = DEFINED_SYSTEMS.lock().unwrap();
-    let system = if let Some(system) = systems.get(&name) {
-        system
+    let ident = match path.segments.pop() {
+        Some(segment) => segment.into_value().ident,
+        None => return Err(parser.error("Expected system name"))
+    };
+
+    let prefix = if path.segments.is_empty() {
+        TokenStream2::new()
     } else {
-        ctx.span_err(macro_span, &format!("Implementing object for undefined system '{}'", name));
-        return DummyResult::any(macro_span);
+        quote!(#path)
     };
 
-    let mut parser = ctx.new_parser_from_tts(&tts);
+    Ok((prefix, ident))
+}
 
-    let obj = match parser.parse_ident() {
-        Ok(ident) => ident,
+fn lookup_system_macro(parser: ParseStream, source: TokenStream2) -> syn::Result<TokenStream2> {
+    let (prefix, ident) = parse_system_path(parser)?;
 
-        Err(mut err) => {
-            err.emit();
-            return DummyResult::any(macro_span);
-        }
+    if parser.peek(Token![<]) {
+        parser.parse::<AngleBracketedGenericArguments>()?;
+    }
+
+    let content;
+    braced!(content in parser);
+    content.parse::<TokenStream2>()?;
+
+    let system_macro = util::system_macro_ident(&ident);
+    Ok(quote!(#prefix #system_macro! { #source }))
+}
+
+fn impl_object_macro(parser: ParseStream) -> syn::Result<TokenStream2> {
+    parser.parse::<Token![@]>()?;
+
+    let definition;
+    braced!(definition in parser);
+    let system = define_system_macro(&definition)?;
+
+    let (prefix, _) = parse_system_path(parser)?;
+
+    let args: Option<AngleBracketedGenericArguments> = if parser.peek(Token![<]) {
+        Some(parser.parse()?)
+    } else {
+        None
     };
 
-    if let Err(mut err) = parser.expect(&Token::Colon) {
-        err.emit();
-        return DummyResult::any(macro_span);
-    }
+    let content;
+    braced!(content in parser);
+
+    let obj: Ident = content.parse()?;
 
+    // Listing handlers is optional, any that are not listed are inferred from the object's impls,
+    // unless the object is implemented for every instantiation of a system with type parameters,
+    // which must then list every handler
     let mut impls = Vec::new();
 
+    if content.peek(Token![:]) {
+        content.parse::<Token![:]>()?;
+    }
+
     loop {
-        if parser.check(&Eof) {
+        if content.is_empty() {
             break
         }
 
-        match parser.parse_ident() {
-            Ok(ident) => impls.push(format!("{}", ident)),
+        let handler: Ident = content.parse()?;
+        let name = handler.to_string();
 
-            Err(mut err) => {
-                err.emit();
-                return DummyResult::any(macro_span);
-            }
+        if !system.handlers.iter().any(|info| info.name == name) {
+            let names: Vec<String> = system.handlers.iter().map(|info| info.name.to_string()).collect();
+            let message = match util::closest_match(&name, names.iter().map(|name| name.as_str())) {
+                Some(closest) => format!("System '{}' has no handler '{}', did you mean '{}'?", system.name, name, closest),
+                None => format!("System '{}' has no handler '{}'", system.name, name)
+            };
+
+            return Err(syn::Error::new(handler.span(), message));
         }
 
-        if !parser.check(&Token::Comma) {
+        if impls.contains(&name) {
+            return Err(syn::Error::new(handler.span(), format!("Handler '{}' is listed more than once", name)));
+        }
+
+        impls.push(name);
+
+        if !content.peek(Token![,]) {
             break
         } else {
-            parser.expect(&Token::Comma).unwrap();
+            content.parse::<Token![,]>()?;
         }
     }
 
-    system.generate_object_impl(obj, &impls)
-}
+    let unlisted: Vec<String> = system.handlers.iter()
+        .map(|handler| handler.name.to_string())
+        .filter(|name| !impls.contains(name))
+        .collect();
 
-fn parse_handler_definition(ctx: &mut ExtCtxt, parser: &mut Parser) -> Option<HandlerInfo> {
-    let mut handler = match parser.parse_ident() {
-        Ok(ident) => HandlerInfo::new(ident),
+    if !unlisted.is_empty() && !system.infers_handlers(args.as_ref()) {
+        let message = format!(
+            "System '{}' has type parameters, so handlers cannot be inferred for '{}': list {}, or give the system arguments such as '{}<..>'",
+            system.name, obj, unlisted.join(", "), system.name
+        );
+
+        return Err(syn::Error::new(obj.span(), message));
+    }
+
+    // Implementing the object for every instantiation repeats the system's bounds, which are
+    // resolved where the object is implemented
+    if args.is_none() && !prefix.is_empty() {
+        check_bound_paths(&system)?;
+    }
+
+    Ok(system.generate_object_impl(obj, &prefix, args.as_ref(), &impls))
+}
 
-        Err(mut err) => {
-            err.emit();
-            return None
+// Bounds on a system's type parameters must name traits by paths that resolve from any module
+fn check_bound_paths(system: &SystemInfo) -> syn::Result<()> {
+    let params = system.generics.type_params().flat_map(|param| param.bounds.iter());
+    let predicates = system.generics.where_clause.iter()
+        .flat_map(|clause| clause.predicates.iter())
+        .filter_map(|predicate| match *predicate {
+            WherePredicate::Type(ref predicate) => Some(predicate.bounds.iter()),
+            _ => None
+        })
+        .flatten();
+
+    for bound in params.chain(predicates) {
+        let path = match *bound {
+            TypeParamBound::Trait(ref bound) => &bound.path,
+            _ => continue
+        };
+
+        let first = match path.segments.first() {
+            Some(segment) => segment.ident.to_string(),
+            None => continue
+        };
+
+        let relative = path.leading_colon.is_none() && match path.segments.len() {
+            1 => !util::PRELUDE_TRAITS.contains(&first.as_str()),
+            _ => first == "self" || first == "super"
+        };
+
+        if relative {
+            let name = &path.segments.last().unwrap().ident;
+            let message = format!(
+                "Bound '{}' of system '{}' cannot be found outside the system's module, name it by its full path such as 'crate::path::to::{}'",
+                name, system.name, name
+            );
+
+            return Err(syn::Error::new_spanned(path, message));
         }
+    }
+
+    Ok(())
+}
+
+fn parse_handler_definition(parser: ParseStream, system: &SystemInfo) -> syn::Result<HandlerInfo> {
+    let attrs = parser.call(Attribute::parse_outer)?;
+
+    // Handlers are as visible as their system unless given a visibility of their own
+    let vis = match parser.parse()? {
+        Visibility::Inherited => system.vis.clone(),
+        vis => vis
     };
 
-    if parser.check(&Token::Colon) {
-        parser.expect(&Token::Colon).unwrap();
+    let mut handler = HandlerInfo::new(vis, parser.parse()?);
+    handler.set_attrs(attrs);
+
+    if parser.peek(Token![:]) {
+        parser.parse::<Token![:]>()?;
 
         loop {
-            if parser.check(&Token::OpenDelim(DelimToken::Brace)) {
+            if parser.peek(token::Brace) {
                 break
             }
 
-            match parser.parse_ident() {
-                Ok(ident) => handler.add_requirement(ident),
+            handler.add_requirement(parser.parse()?);
 
-                Err(mut err) => {
-                    err.emit();
-                    return None
-                }
-            };
-
-            if !parser.check(&Token::Comma) {
+            if !parser.peek(Token![,]) {
                 break
             } else {
-                parser.expect(&Token::Comma).unwrap();
+                parser.parse::<Token![,]>()?;
             }
         }
     }
 
-    match parser.parse_token_tree() {
-        Ok(TokenTree::Delimited(span, ref tts)) => {
-            let mut handler_parser = ctx.new_parser_from_tts(&tts.tts);
-
-            if handler_parser.check(&Eof) {
-                ctx.span_err(span, "Expected delimited list of handler functions");
-                return None
-            }
+    if !parser.peek(token::Brace) {
+        return Err(parser.error("Expected delimited list of handler functions"));
+    }
 
-            loop {
-                if handler_parser.check(&Eof) {
-                    break
-                }
+    let content;
+    let brace = braced!(content in parser);
 
-                match parse_handler_function_definition(ctx, &mut handler_parser) {
-                    Some(function) => handler.add_function(function),
-                    None => ()
-                };
+    if content.is_empty() {
+        return Err(syn::Error::new(brace.span.join(), "Expected delimited list of handler functions"));
+    }
 
-                if !handler_parser.check(&Token::Semi) {
-                    break
-                } else {
-                    handler_parser.expect(&Token::Semi).unwrap();
-                }
-            }
-        },
+    loop {
+        if content.is_empty() {
+            break
+        }
 
-        Ok(ref tt) => {
-            ctx.span_err(tt.get_span(), "Expected delimited list of handler functions");
-            return None
-        },
+        handler.add_function(parse_handler_function_definition(&content, system)?);
 
-        Err(mut err) => {
-            err.emit();
-            return None
+        if !content.peek(Token![;]) {
+            break
+        } else {
+            content.parse::<Token![;]>()?;
         }
     }
 
-    Some(handler)
+    Ok(handler)
 }
 
-fn parse_handler_function_definition(ctx: &mut ExtCtxt, parser: &mut Parser) -> Option<HandlerFnInfo> {
-    let source = match parser.parse_ident() {
-        Ok(ident) => ident,
+fn parse_handler_function_definition(parser: ParseStream, system: &SystemInfo) -> syn::Result<HandlerFnInfo> {
+    let mut attrs = parser.call(Attribute::parse_outer)?;
+
+    // `#[default]` is not forwarded, it gives the slot an empty default implementation
+    let default = attrs.iter()
+        .position(|attr| attr.path().is_ident("default"))
+        .map(|idx| attrs.remove(idx));
+
+    let queue = if parser.peek(kw::queue) && parser.peek2(Ident) {
+        parser.parse::<kw::queue>()?;
+        true
+    } else {
+        false
+    };
 
-        Err(mut err) => {
-            err.emit();
-            return None
+    let asynchronous = if parser.peek(Token![async]) && parser.peek2(Ident) {
+        let asynchronous = parser.parse::<Token![async]>()?;
+
+        if queue {
+            return Err(syn::Error::new(asynchronous.span, "Async signals cannot be given the system's queue"));
         }
+
+        true
+    } else {
+        false
     };
 
-    let args = match parser.parse_token_tree() {
-        Ok(TokenTree::Delimited(_, ref tts)) => {
-            let mut arg_parser = ctx.new_parser_from_tts(&tts.tts);
-            let mut args = Vec::new();
+    let parallel = if parser.peek(kw::par) && parser.peek2(Ident) {
+        let parallel = parser.parse::<kw::par>()?;
 
-            loop {
-                if arg_parser.check(&Eof) {
-                    break
-                }
+        if queue || asynchronous {
+            return Err(syn::Error::new(parallel.span, "Parallel signals cannot be async or be given the system's queue"));
+        }
 
-                match parse_handler_function_arg(ctx, &mut arg_parser) {
-                    Some(arg) => args.push(arg),
-                    None => ()
-                }
+        Some(parallel)
+    } else {
+        None
+    };
 
-                if !arg_parser.check(&Token::Comma) {
-                    break
-                } else {
-                    arg_parser.expect(&Token::Comma).unwrap();
-                }
-            }
+    let source: Ident = parser.parse()?;
+
+    if !parser.peek(token::Paren) {
+        return Err(parser.error("Expected function argument list"));
+    }
 
-            args
-        },
+    let content;
+    parenthesized!(content in parser);
 
-        Ok(ref tt) => {
-            ctx.span_err(tt.get_span(), "Expected function argument list");
-            return None
-        },
+    // Signals taking `&self` only need shared access to their handlers
+    let receiver = if content.peek(Token![&]) && content.peek2(Token![self]) {
+        content.parse::<Token![&]>()?;
+        let receiver = content.parse::<Token![self]>()?;
 
-        Err(mut err) => {
-            err.emit();
-            return None
+        if !content.is_empty() {
+            content.parse::<Token![,]>()?;
         }
-    };
 
-    if let Err(mut err) = parser.expect(&Token::FatArrow) {
-        err.emit();
-        return None
+        Some(receiver)
+    } else {
+        None
     };
 
-    let dest = match parser.parse_ident() {
-        Ok(ident) => ident,
+    let mut args = Vec::new();
 
-        Err(mut err) => {
-            err.emit();
-            return None
+    loop {
+        if content.is_empty() {
+            break
         }
+
+        args.push(parse_handler_function_arg(&content)?);
+
+        if !content.peek(Token![,]) {
+            break
+        } else {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    let ret = if parser.peek(Token![->]) {
+        parser.parse::<Token![->]>()?;
+        Some(parser.parse::<Type>()?)
+    } else {
+        None
     };
 
-    Some(HandlerFnInfo::new(source, dest, args))
-}
+    parser.parse::<Token![=>]>()?;
+
+    let dest: Ident = parser.parse()?;
+
+    let mut function = HandlerFnInfo::new(source, dest, args);
+    function.set_attrs(attrs);
 
-fn parse_handler_function_arg(_: &mut ExtCtxt, parser: &mut Parser) -> Option<HandlerFnArg> {
-    let name = match parser.parse_ident() {
-        Ok(ident) => ident,
+    if asynchronous {
+        function.set_async(system.is_send());
+    }
 
-        Err(mut err) => {
-            err.emit();
-            return None
+    if let Some(receiver) = receiver {
+        if queue {
+            return Err(syn::Error::new_spanned(receiver, "Signals taking &self cannot be given the system's queue"));
         }
-    };
 
-    if let Err(mut err) = parser.expect(&Token::Colon) {
-        err.emit();
-        return None
+        function.set_immutable();
     }
 
-    let ptr = if parser.check(&Token::BinOp(BinOpToken::And)) {
-        parser.expect(&Token::BinOp(BinOpToken::And)).unwrap();
+    if queue {
+        let queue_name = system.queue_name();
+        function.set_queue(parse_quote!(#queue_name));
+    }
 
-        parser.parse_mutability().ok()
-    } else {
-        None
-    };
+    if parser.peek(token::Bracket) {
+        let content;
+        let bracket = bracketed!(content in parser);
+
+        let ident: Ident = content.parse()?;
+        let aggregate = match Aggregate::from_ident(&ident) {
+            Some(aggregate) => aggregate,
+            None => return Err(syn::Error::new(
+                ident.span(),
+                format!("Unknown result aggregation '{}', expected one of collect, first, any, all, sum, last or propagate", ident)
+            ))
+        };
+
+        match (ret, aggregate) {
+            (None, Aggregate::Propagate) => {
+                let propagation = system.propagation_name();
+                function.set_return(parse_quote!(#propagation), aggregate);
+            },
+
+            (Some(ty), Aggregate::Propagate) => return Err(syn::Error::new_spanned(
+                ty,
+                "Propagating signals cannot declare a return type, their slots return the system's propagation enum"
+            )),
+
+            (Some(ty), _) => function.set_return(ty, aggregate),
+            (None, _) => return Err(syn::Error::new(bracket.span.join(), "Result aggregation given for a signal with no return type"))
+        }
+    } else if let Some(ty) = ret {
+        function.set_return(ty, Aggregate::Collect);
+    }
 
-    let ty = match parser.parse_ident() {
-        Ok(ident) => ident,
+    if let Some(parallel) = parallel {
+        // Every slot of a parallel signal is called, so it cannot be stopped
+        if function.aggregate == Aggregate::Propagate {
+            return Err(syn::Error::new(parallel.span, "Parallel signals cannot propagate"));
+        }
 
-        Err(mut err) => {
-            err.emit();
-            return None
+        function.set_parallel();
+    }
+
+    if parser.peek(token::Brace) {
+        let block: Block = parser.parse()?;
+
+        if let Some(attr) = default {
+            return Err(syn::Error::new_spanned(attr, "Slot marked #[default] cannot also be given a body"));
         }
+
+        function.set_default(block);
+    } else if default.is_some() {
+        function.set_default(function.default_block());
+    }
+
+    Ok(function)
+}
+
+fn parse_handler_function_arg(parser: ParseStream) -> syn::Result<HandlerFnArg> {
+    let pass = if parser.peek(Token![ref]) {
+        parser.parse::<Token![ref]>()?;
+        ArgPass::Ref
+    } else if parser.peek(kw::clone) && parser.peek2(Ident) {
+        parser.parse::<kw::clone>()?;
+        ArgPass::Clone
+    } else {
+        ArgPass::Value
     };
 
-    Some(HandlerFnArg::new(name, ty, ptr))
+    let name: Ident = parser.parse()?;
+
+    parser.parse::<Token![:]>()?;
+
+    let ty: Type = parser.parse()?;
+
+    Ok(HandlerFnArg::new(name, ty, pass))
 }
@@ -16,25 +16,28 @@
 //  limitations under the License.
 //////////////////////////////////////////////////////////////////////////////
 
-use syntax::ast::*;
-use syntax::ptr::P;
-use syntax::codemap::Span;
-use syntax::ext::base::{MacResult, MacEager};
-use syntax::util::small_vector::SmallVector;
-use syntax::parse::token::{str_to_ident, InternedString};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, AngleBracketedGenericArguments, Attribute, Block, GenericArgument, GenericParam, Generics, Ident, Lifetime, Type, TypeParamBound, Visibility};
 
-use ::util;
+use crate::util;
+use crate::util::str_to_ident;
 
 #[derive(Debug, Clone)]
 pub struct SystemInfo {
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
     pub name: Ident,
-    pub span: Span,
+    pub generics: Generics,
+    pub object_bounds: Vec<TypeParamBound>,
     pub reqs: Vec<Ident>,
     pub handlers: Vec<HandlerInfo>
 }
 
 #[derive(Debug, Clone)]
 pub struct HandlerInfo {
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
     pub name: Ident,
     pub reqs: Vec<Ident>,
     pub fns: Vec<HandlerFnInfo>
@@ -42,28 +45,88 @@ pub struct HandlerInfo {
 
 #[derive(Debug, Clone)]
 pub struct HandlerFnInfo {
+    pub attrs: Vec<Attribute>,
     pub source_name: Ident,
     pub dest_name: Ident,
-    pub args: Vec<HandlerFnArg>
+    pub args: Vec<HandlerFnArg>,
+    pub ret: Option<Type>,
+    pub aggregate: Aggregate,
+    pub queue: Option<Type>,
+    pub default: Option<Block>,
+    pub immutable: bool,
+    pub parallel: bool,
+    pub asynchronous: bool,
+    pub send_future: bool
+}
+
+/// How the values returned by each slot of a signal are combined into the signal's result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Every result, in dispatch order, as a `Vec`.
+    Collect,
+    /// The first `Some` returned. Slots must return an `Option`.
+    First,
+    /// Whether any slot returned `true`.
+    Any,
+    /// Whether every slot returned `true`.
+    All,
+    /// The sum of every result, starting from `Default::default()`.
+    Sum,
+    /// The result of the last slot called, or `None` if there were no handlers.
+    Last,
+    /// Whether a slot stopped the signal by returning `Stop` from the system's propagation enum.
+    /// Slots after the one that stopped the signal are not called.
+    Propagate
 }
 
 #[derive(Debug, Clone)]
 pub struct HandlerFnArg {
     pub name: Ident,
-    pub ty: Ident,
-    pub ptr: Option<Mutability>
+    pub ty: Type,
+    pub pass: ArgPass
+}
+
+/// How a signal's argument is given to each slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgPass {
+    /// The argument itself, which must be `Copy` unless the signal is only ever sent to one slot.
+    Value,
+    /// A reference to the argument, marked with `ref`.
+    Ref,
+    /// A clone of the argument for each slot, marked with `clone`.
+    Clone
 }
 
 impl SystemInfo {
-    pub fn new(name: Ident, span: Span) -> SystemInfo {
+    pub fn new(vis: Visibility, name: Ident) -> SystemInfo {
         SystemInfo {
-            name: name,
-            span: span,
+            attrs: Vec::new(),
+            vis,
+            name,
+            generics: Generics::default(),
+            object_bounds: Vec::new(),
             reqs: Vec::new(),
             handlers: Vec::new()
         }
     }
 
+    pub fn set_attrs(&mut self, attrs: Vec<Attribute>) {
+        self.attrs = attrs;
+    }
+
+    pub fn set_generics(&mut self, generics: Generics) {
+        self.generics = generics;
+    }
+
+    pub fn set_object_bounds(&mut self, bounds: Vec<TypeParamBound>) {
+        self.object_bounds = bounds;
+    }
+
+    // Whether every object is required to be `Send`
+    pub fn is_send(&self) -> bool {
+        self.object_bounds.iter().any(|bound| matches!(*bound, TypeParamBound::Trait(ref bound) if bound.path.segments.last().is_some_and(|segment| segment.ident == "Send")))
+    }
+
     pub fn add_requirement(&mut self, req: Ident) {
         self.reqs.push(req);
     }
@@ -73,558 +136,1105 @@ impl SystemInfo {
     }
 
     fn object_name(&self) -> Ident {
-        util::ident_append(self.name, str_to_ident("Object"))
+        util::ident_append(&self.name, &str_to_ident("Object"))
     }
 
     fn idx_name(&self) -> Ident {
-        util::ident_append(self.name, str_to_ident("Index"))
+        util::ident_append(&self.name, &str_to_ident("Index"))
+    }
+
+    pub fn propagation_name(&self) -> Ident {
+        util::ident_append(&self.name, &str_to_ident("Propagation"))
+    }
+
+    pub fn queue_name(&self) -> Ident {
+        util::ident_append(&self.name, &str_to_ident("Queue"))
+    }
+
+    fn guard_name(&self) -> Ident {
+        util::ident_append(&self.name, &str_to_ident("Guard"))
+    }
+
+    fn connection_name(&self) -> Ident {
+        util::ident_append(&self.name, &str_to_ident("Connection"))
+    }
+
+    fn shared_name(&self) -> Ident {
+        util::ident_append(&self.name, &str_to_ident("Shared"))
     }
 
-    fn generate_object_trait(&self) -> Item {
+    fn event_name(&self) -> Ident {
+        util::ident_append(&self.name, &str_to_ident("Event"))
+    }
+
+    // The event enum is not generic, so signals using the system's type parameters cannot be queued
+    fn queued_fns(&self) -> Vec<(&HandlerInfo, &HandlerFnInfo)> {
+        let params: Vec<Ident> = self.generics.params.iter().filter_map(|param| match *param {
+            GenericParam::Type(ref param) => Some(param.ident.clone()),
+            GenericParam::Const(ref param) => Some(param.ident.clone()),
+            GenericParam::Lifetime(_) => None
+        }).collect();
+
+        self.handlers.iter()
+            .flat_map(|handler| handler.fns.iter().map(move |func| (handler, func)))
+            .filter(|&(_, func)| func.is_queueable() && !func.args.iter().any(|arg| util::mentions_any(&arg.ty, &params)))
+            .collect()
+    }
+
+    fn cfgs(&self) -> Vec<&Attribute> {
+        util::cfg_attrs(&self.attrs)
+    }
+
+    fn ty(&self) -> TokenStream {
+        let name = &self.name;
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        quote!(#name #ty_generics)
+    }
+
+    fn object_ty(&self) -> TokenStream {
+        let object_name = self.object_name();
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        quote!(#object_name #ty_generics)
+    }
+
+    fn boxed_object_ty(&self) -> TokenStream {
+        let object_ty = self.object_ty();
+        let bounds = &self.object_bounds;
+        quote!(Box<dyn #object_ty #(+ #bounds)*>)
+    }
+
+    fn shared_ty(&self) -> TokenStream {
+        let shared_name = self.shared_name();
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        quote!(#shared_name #ty_generics)
+    }
+
+    fn generate_object_trait(&self) -> TokenStream {
         let mut fns = Vec::new();
 
         for handler in self.handlers.iter() {
-            fns.push(handler.generate_as_self());
-            fns.push(handler.generate_as_self_mut());
+            fns.push(handler.generate_as_self(&self.generics));
+            fns.push(handler.generate_as_self_mut(&self.generics));
         }
 
         util::create_trait(
+            &self.vis,
             self.object_name(),
+            &self.generics,
             &self.reqs,
             &fns
         )
     }
 
-    fn generate_idx_struct(&self) -> Item {
-        let mut item = util::create_tuple_struct(
-            self.idx_name(),
-            vec![P(util::ty_from_ident(str_to_ident("usize")))]
-        );
+    fn generate_idx_struct(&self) -> TokenStream {
+        let derive = util::create_derive(vec!["Copy", "Clone", "Eq", "PartialEq"]);
+        let item = util::create_tuple_struct(&self.vis, self.idx_name(), vec![quote!(usize), quote!(u32)]);
 
-        item.attrs = vec![util::create_derive(vec![
-            InternedString::new("Copy"),
-            InternedString::new("Clone"),
-            InternedString::new("Eq"),
-            InternedString::new("PartialEq"),
-        ])];
+        quote! {
+            #derive
+            #item
+        }
+    }
 
-        item
+    fn generate_guard_struct(&self) -> TokenStream {
+        let idx_name = self.idx_name();
+        let guard_name = self.guard_name();
+
+        let item = util::create_struct(&self.vis, guard_name.clone(), &Generics::default(), vec![
+            util::create_struct_field(str_to_ident("idx"), quote!(#idx_name)),
+            util::create_struct_field(str_to_ident("dropped"), quote!(std::sync::Weak<(std::sync::atomic::AtomicBool, std::sync::Mutex<Vec<#idx_name>>)>))
+        ]);
+
+        let methods = util::create_impl(quote!(#guard_name), &Generics::default(), None, vec![
+            util::impl_method(
+                str_to_ident("idx"),
+                &[],
+                Vec::new(),
+                Some(quote!(#idx_name)),
+                quote!(self.idx)
+            ),
+
+            // Keeps the object in the system, which can then only be removed by its index
+            quote! {
+                pub fn release(mut self) -> #idx_name {
+                    self.dropped = std::sync::Weak::new();
+                    self.idx
+                }
+            }
+        ]);
+
+        // Nothing is left to remove if the system was dropped first
+        let drop = util::create_impl(quote!(#guard_name), &Generics::default(), Some(quote!(Drop)), vec![quote! {
+            fn drop(&mut self) {
+                if let Some(dropped) = self.dropped.upgrade() {
+                    if let Ok(mut idxs) = dropped.1.lock() {
+                        idxs.push(self.idx);
+                        dropped.0.store(true, std::sync::atomic::Ordering::Release);
+                    }
+                }
+            }
+        }]);
+
+        // Only constructed by `add_scoped`, which a system defined by a local macro may never call
+        quote! {
+            #[allow(dead_code)]
+            #item
+            #methods
+            #drop
+        }
     }
 
-    fn generate_struct(&self) -> Item {
-        let mut fields = vec![
-            util::create_struct_field(
-                str_to_ident("objects"), 
-                P(util::param_ty_from_ident(
-                    str_to_ident("Vec"),
-                    util::param_ty_from_ident(
-                        str_to_ident("Box"),
-                        util::ty_from_ident(self.object_name())
-                    )
-                ))
+    fn generate_connection_struct(&self) -> TokenStream {
+        let derive = util::create_derive(vec!["Copy", "Clone", "Eq", "PartialEq"]);
+        let item = util::create_tuple_struct(&self.vis, self.connection_name(), vec![quote!(usize)]);
+
+        quote! {
+            #derive
+            #item
+        }
+    }
+
+    // Async signals cannot have closures connected, since closures cannot return borrowing futures
+    fn connectable_fns(&self) -> Vec<(&HandlerInfo, &HandlerFnInfo)> {
+        self.handlers.iter()
+            .flat_map(|handler| handler.fns.iter().map(move |func| (handler, func)))
+            .filter(|&(_, func)| !func.asynchronous)
+            .collect()
+    }
+
+    fn connection_ty(&self, func: &HandlerFnInfo) -> TokenStream {
+        let lifetimes = func.lifetimes(&self.generics);
+        let bounds = &self.object_bounds;
+
+        let for_lifetimes = if lifetimes.is_empty() {
+            None
+        } else {
+            Some(quote!(for<#(#lifetimes),*>))
+        };
+
+        let queue = func.queue.as_ref().map(|queue| quote!(&mut #queue,));
+        let args = func.args.iter().map(|arg| arg.generate_slot_ty());
+        let ret = func.ret.as_ref().map(|ty| quote!(-> #ty));
+
+        if func.immutable {
+            quote!(#for_lifetimes Fn(#(#args),*) #ret #(+ #bounds)*)
+        } else {
+            quote!(#for_lifetimes FnMut(#queue #(#args),*) #ret #(+ #bounds)*)
+        }
+    }
+
+    fn generate_fn_connect_impls(&self, items: &mut Vec<TokenStream>) {
+        let connection_name = self.connection_name();
+
+        for (handler, func) in self.connectable_fns() {
+            let connections = func.connections_ident();
+            let connection_ty = self.connection_ty(func);
+
+            let attrs = handler.cfgs().into_iter().chain(func.attrs.iter());
+            let item = util::impl_mut_method(
+                util::ident_append(&str_to_ident("connect_"), &func.source_name),
+                &[],
+                vec![util::create_arg(str_to_ident("slot"), quote!(impl #connection_ty + 'static))],
+                Some(quote!(#connection_name)),
+                quote! {
+                    let connection = #connection_name(self.next_connection);
+                    self.next_connection += 1;
+                    self.#connections.push((connection, Box::new(slot)));
+                    connection
+                }
+            );
+
+            items.push(quote!(#(#attrs)* #item));
+        }
+    }
+
+    fn generate_fn_disconnect_impl(&self) -> TokenStream {
+        let connection_name = self.connection_name();
+
+        let checks = self.connectable_fns().into_iter().map(|(handler, func)| {
+            let connections = func.connections_ident();
+            let cfgs = handler.cfgs().into_iter().chain(func.cfgs());
+
+            quote! {
+                #(#cfgs)*
+                if let Some(pos) = self.#connections.iter().position(|&(other, _)| other == connection) {
+                    drop(self.#connections.remove(pos));
+                    return true;
+                }
+            }
+        });
+
+        util::impl_mut_method(
+            str_to_ident("disconnect"),
+            &[],
+            vec![util::create_arg(str_to_ident("connection"), quote!(#connection_name))],
+            Some(quote!(bool)),
+            quote! {
+                #(#checks)*
+                false
+            }
+        )
+    }
+
+    fn generate_propagation_enum(&self) -> TokenStream {
+        let derive = util::create_derive(vec!["Copy", "Clone", "Debug", "Eq", "PartialEq"]);
+        let item = util::create_enum(&self.vis, self.propagation_name(), vec![str_to_ident("Continue"), str_to_ident("Stop")]);
+
+        // Generated even for systems without a `propagate` signal
+        quote! {
+            #derive
+            #[allow(dead_code)]
+            #item
+        }
+    }
+
+    fn generate_event_enum(&self) -> TokenStream {
+        let event_name = self.event_name();
+        let variants = self.queued_fns().into_iter().map(|(handler, func)| {
+            let cfgs = handler.cfgs();
+            let variant = func.generate_event_variant();
+            quote!(#(#cfgs)* #variant)
+        }).collect();
+
+        // `#[event(...)]` on the system is forwarded to the event enum
+        let attrs = util::nested_attrs(&self.attrs, "event");
+        let item = util::create_struct_enum(&self.vis, event_name, variants);
+
+        quote! {
+            #(#attrs)*
+            #item
+        }
+    }
+
+    fn generate_queue_struct(&self) -> TokenStream {
+        let event_name = self.event_name();
+
+        util::create_struct(&self.vis, self.queue_name(), &Generics::default(), vec![
+            util::create_struct_field(str_to_ident("events"), quote!(std::collections::VecDeque<#event_name>))
+        ])
+    }
+
+    fn generate_queue_impl(&self) -> TokenStream {
+        let event_name = self.event_name();
+
+        let mut fns = vec![
+            util::impl_mut_method(
+                str_to_ident("emit"),
+                &[],
+                vec![util::create_arg(str_to_ident("event"), quote!(#event_name))],
+                None,
+                quote!(self.events.push_back(event);)
             ),
 
-            util::create_struct_field(
-                str_to_ident("idxs"), 
-                P(util::param_ty_from_ident(
-                    str_to_ident("Vec"),
-                    util::param_ty_from_ident(
-                        str_to_ident("Option"),
-                        util::ty_from_ident(str_to_ident("usize")),
-                    )
-                ))
+            util::impl_method(
+                str_to_ident("len"),
+                &[],
+                Vec::new(),
+                Some(quote!(usize)),
+                quote!(self.events.len())
+            ),
+
+            util::impl_method(
+                str_to_ident("is_empty"),
+                &[],
+                Vec::new(),
+                Some(quote!(bool)),
+                quote!(self.events.is_empty())
             ),
         ];
 
-        for handler in self.handlers.iter() {
-            fields.push(util::create_struct_field(
-                util::idxs_ident(handler.name),
-                P(util::param_ty_from_ident(
-                    str_to_ident("Vec"),
-                    util::ty_from_ident(str_to_ident("usize"))
-                ))
-            ));
+        for (handler, func) in self.queued_fns() {
+            let variant = func.event_variant_name();
+            let arg_names: Vec<&Ident> = func.args.iter().map(|arg| &arg.name).collect();
+
+            let cfgs = handler.cfgs().into_iter().chain(func.cfgs());
+            let item = util::impl_mut_method(
+                func.queue_fn_name(),
+                &[],
+                func.args.iter().map(|arg| arg.generate()).collect(),
+                None,
+                quote!(self.events.push_back(#event_name::#variant { #(#arg_names),* });)
+            );
+
+            fns.push(quote!(#(#cfgs)* #item));
+        }
+
+        let queue_name = self.queue_name();
+        util::create_impl(quote!(#queue_name), &Generics::default(), None, fns)
+    }
+
+    fn generate_fn_emit_impl(&self) -> TokenStream {
+        let event_name = self.event_name();
+
+        util::impl_mut_method(
+            str_to_ident("emit"),
+            &[],
+            vec![util::create_arg(str_to_ident("event"), quote!(#event_name))],
+            None,
+            quote!(self.queue.emit(event);)
+        )
+    }
+
+    fn generate_fn_queue_impls(&self, items: &mut Vec<TokenStream>) {
+        for (handler, func) in self.queued_fns() {
+            let queue_fn_name = func.queue_fn_name();
+            let arg_names: Vec<&Ident> = func.args.iter().map(|arg| &arg.name).collect();
+
+            let attrs = handler.cfgs().into_iter().chain(func.attrs.iter());
+            let item = util::impl_mut_method(
+                queue_fn_name.clone(),
+                &[],
+                func.args.iter().map(|arg| arg.generate()).collect(),
+                None,
+                quote!(self.queue.#queue_fn_name(#(#arg_names),*);)
+            );
+
+            items.push(quote!(#(#attrs)* #item));
         }
+    }
+
+    fn generate_fn_dispatch_impl(&self) -> TokenStream {
+        let event_name = self.event_name();
+
+        let arms = self.queued_fns().into_iter().map(|(handler, func)| {
+            let variant = func.event_variant_name();
+            let source_name = &func.source_name;
+            let arg_names: Vec<&Ident> = func.args.iter().map(|arg| &arg.name).collect();
+            let cfgs = handler.cfgs().into_iter().chain(func.cfgs());
+
+            quote! {
+                #(#cfgs)*
+                #event_name::#variant { #(#arg_names),* } => {
+                    self.#source_name(#(#arg_names),*);
+                }
+            }
+        });
+
+        let item = util::impl_mut_method(
+            str_to_ident("dispatch"),
+            &[],
+            vec![util::create_arg(str_to_ident("event"), quote!(#event_name))],
+            None,
+            quote! {
+                match event {
+                    #(#arms)*
+                }
+            }
+        );
+
+        // Queued signals may themselves be deprecated
+        quote! {
+            #[allow(deprecated)]
+            #item
+        }
+    }
+
+    fn generate_fn_process_events_impl(&self) -> TokenStream {
+        // Events queued by slots while processing are handled in the same call
+        util::impl_mut_method(
+            str_to_ident("process_events"),
+            &[],
+            Vec::new(),
+            None,
+            quote! {
+                self.remove_dropped();
 
-        util::create_struct(self.name, fields)
+                while let Some(event) = self.queue.events.pop_front() {
+                    self.dispatch(event);
+                }
+            }
+        )
     }
 
-    fn generate_fn_new_impl(&self) -> ImplItem {
+    fn generate_struct(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
+        let queue_name = self.queue_name();
+
         let mut fields = vec![
-            util::create_field(
-                str_to_ident("objects"),
-                P(util::vec_new())
-            ),
-            util::create_field(
-                str_to_ident("idxs"),
-                P(util::vec_new())
-            ),
+            util::create_struct_field(str_to_ident("objects"), quote!(Vec<#boxed_object>)),
+            util::create_struct_field(str_to_ident("owners"), quote!(Vec<usize>)),
+            util::create_struct_field(str_to_ident("idxs"), quote!(Vec<Option<usize>>)),
+            util::create_struct_field(str_to_ident("generations"), quote!(Vec<u32>)),
+            util::create_struct_field(str_to_ident("priorities"), quote!(Vec<i32>)),
+            util::create_struct_field(str_to_ident("free"), quote!(Vec<usize>)),
+            util::create_struct_field(str_to_ident("queue"), quote!(#queue_name)),
+            util::create_struct_field(str_to_ident("dropped"), quote!(std::sync::Arc<(std::sync::atomic::AtomicBool, std::sync::Mutex<Vec<#idx_name>>)>)),
+            util::create_struct_field(str_to_ident("next_connection"), quote!(usize)),
         ];
 
         for handler in self.handlers.iter() {
-            fields.push(util::create_field(
-                util::idxs_ident(handler.name),
-                P(util::vec_new())
-            ));
+            let cfgs = handler.cfgs();
+            let field = util::create_struct_field(
+                util::idxs_ident(&handler.name),
+                quote!(Vec<#idx_name>)
+            );
+
+            fields.push(quote!(#(#cfgs)* #field));
         }
 
+        let connection_name = self.connection_name();
+
+        for (handler, func) in self.connectable_fns() {
+            let cfgs = handler.cfgs().into_iter().chain(func.cfgs());
+            let connection_ty = self.connection_ty(func);
+            let field = util::create_struct_field(
+                func.connections_ident(),
+                quote!(Vec<(#connection_name, Box<dyn #connection_ty>)>)
+            );
+
+            fields.push(quote! {
+                #(#cfgs)*
+                #[allow(clippy::type_complexity)]
+                #field
+            });
+        }
+
+        let attrs = self.attrs.iter().filter(|attr| !attr.path().is_ident("event"));
+        let item = util::create_struct(&self.vis, self.name.clone(), &self.generics, fields);
+
+        quote! {
+            #(#attrs)*
+            #item
+        }
+    }
+
+    fn generate_fn_new_impl(&self) -> TokenStream {
+        let queue_name = self.queue_name();
+        let handler_idxs: Vec<TokenStream> = self.handlers.iter().map(|handler| {
+            let cfgs = handler.cfgs();
+            let handler_idxs = util::idxs_ident(&handler.name);
+            quote!(#(#cfgs)* #handler_idxs: Vec::new())
+        }).collect();
+
+        let connections: Vec<TokenStream> = self.connectable_fns().into_iter().map(|(handler, func)| {
+            let cfgs = handler.cfgs().into_iter().chain(func.cfgs());
+            let connections = func.connections_ident();
+            quote!(#(#cfgs)* #connections: Vec::new())
+        }).collect();
+
         util::impl_static_method(
             str_to_ident("new"),
             Vec::new(),
-            Some(P(util::ty_from_ident(self.name))),
-            P(util::create_block(
-                Vec::new(),
-                Some(P(util::create_struct_expr(self.name, fields)))
-            ))
+            Some(quote!(Self)),
+            quote! {
+                Self {
+                    objects: Vec::new(),
+                    owners: Vec::new(),
+                    idxs: Vec::new(),
+                    generations: Vec::new(),
+                    priorities: Vec::new(),
+                    free: Vec::new(),
+                    queue: #queue_name { events: std::collections::VecDeque::new() },
+                    dropped: std::sync::Arc::new((std::sync::atomic::AtomicBool::new(false), std::sync::Mutex::new(Vec::new()))),
+                    next_connection: 0,
+                    #(#handler_idxs,)*
+                    #(#connections,)*
+                }
+            }
         )
     }
 
-    fn generate_fn_add_impl(&self) -> ImplItem {
-        let mut stmts = vec![
-            // let idx = self.idxs.len();
-            util::create_let_stmt(
-                str_to_ident("idx"),
-                Some(P(util::create_method_call(
-                    str_to_ident("len"),
-                    P(util::create_self_field_expr(str_to_ident("idxs"))),
-                    Vec::new()
-                )))
-            ),
+    fn generate_fn_add_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
 
-            // self.idxs.push(Some(self.objects.len()));
-            util::create_stmt(P(util::create_method_call(
-                str_to_ident("push"),
-                P(util::create_self_field_expr(str_to_ident("idxs"))),
-                vec![P(util::create_call(
-                    P(util::create_var_expr(str_to_ident("Some"))),
-                    vec![P(util::create_method_call(
-                        str_to_ident("len"),
-                        P(util::create_self_field_expr(str_to_ident("objects"))),
-                        Vec::new()
-                    ))]
-                ))]
-            ))),
-
-            // self.objects.push(object);
-            util::create_stmt(P(util::create_method_call(
-                str_to_ident("push"),
-                P(util::create_self_field_expr(str_to_ident("objects"))),
-                vec![P(util::create_var_expr(str_to_ident("object")))]
-            ))),
-
-            // let object = self.objects.last().unwrap();
-            util::create_let_stmt(
-                str_to_ident("object"),
-                Some(P(util::create_method_call(
-                    str_to_ident("unwrap"),
-                    P(util::create_method_call(
-                        str_to_ident("last"),
-                        P(util::create_self_field_expr(str_to_ident("objects"))),
-                        Vec::new()
-                    )),
-                    Vec::new()
-                )))
-            )
-        ];
+        util::impl_mut_method(
+            str_to_ident("add"),
+            &[],
+            vec![util::create_arg(str_to_ident("object"), quote!(#boxed_object))],
+            Some(quote!(#idx_name)),
+            quote!(self.add_with_priority(object, 0))
+        )
+    }
 
-        for handler in self.handlers.iter() {
-            stmts.push(util::create_stmt(P(handler.generate_add_check())));
-        }
+    fn generate_fn_add_with_priority_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
+        let checks: Vec<TokenStream> = self.handlers.iter().map(|handler| handler.generate_add_check()).collect();
 
         util::impl_mut_method(
-            str_to_ident("add"),
-            vec![util::create_arg(
-                str_to_ident("object"), 
-                P(util::param_ty_from_ident(
-                    str_to_ident("Box"),
-                    util::ty_from_ident(self.object_name())
-                ))
-            )],
-            Some(P(util::ty_from_ident(self.idx_name()))),
-            P(util::create_block(
-                stmts, 
-                Some(P(util::create_call(
-                    P(util::create_var_expr(self.idx_name())),
-                    vec![P(util::create_var_expr(str_to_ident("idx")))]
-                )))
-            ))
+            str_to_ident("add_with_priority"),
+            &[],
+            vec![
+                util::create_arg(str_to_ident("object"), quote!(#boxed_object)),
+                util::create_arg(str_to_ident("priority"), quote!(i32))
+            ],
+            Some(quote!(#idx_name)),
+            quote! {
+                self.remove_dropped();
+
+                let idx = if let Some(idx) = self.free.pop() {
+                    self.idxs[idx] = Some(self.objects.len());
+                    self.priorities[idx] = priority;
+                    #idx_name(idx, self.generations[idx])
+                } else {
+                    self.idxs.push(Some(self.objects.len()));
+                    self.generations.push(0);
+                    self.priorities.push(priority);
+                    #idx_name(self.idxs.len() - 1, 0)
+                };
+
+                self.objects.push(object);
+                self.owners.push(idx.0);
+                let object = self.objects.last().unwrap();
+                #(#checks)*
+                idx
+            }
+        )
+    }
+
+    fn generate_fn_add_scoped_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let guard_name = self.guard_name();
+
+        util::impl_mut_method(
+            str_to_ident("add_scoped"),
+            &[],
+            vec![util::create_arg(str_to_ident("object"), quote!(#boxed_object))],
+            Some(quote!(#guard_name)),
+            quote! {
+                #guard_name {
+                    idx: self.add(object),
+                    dropped: std::sync::Arc::downgrade(&self.dropped)
+                }
+            }
+        )
+    }
+
+    fn generate_fn_remove_dropped_impl(&self) -> TokenStream {
+        // The flag is set by guards once their index is pushed, so the lock is only taken while
+        // there is something to remove
+        util::impl_mut_method(
+            str_to_ident("remove_dropped"),
+            &[],
+            Vec::new(),
+            None,
+            quote! {
+                if !self.dropped.0.load(std::sync::atomic::Ordering::Acquire) {
+                    return;
+                }
+
+                let dropped = {
+                    let mut idxs = self.dropped.1.lock().unwrap();
+                    self.dropped.0.store(false, std::sync::atomic::Ordering::Release);
+                    std::mem::take(&mut *idxs)
+                };
+
+                for idx in dropped {
+                    self.remove(idx);
+                }
+            }
         )
     }
 
-    fn generate_fn_iter_impl(&self) -> ImplItem {
+    fn generate_fn_iter_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
+
+        // Objects whose guards were dropped are skipped, as they are by `get`
         util::impl_method(
             str_to_ident("iter"),
+            &[],
             Vec::new(),
-            Some(P(util::path_param_ty(
-                vec![str_to_ident("std"), str_to_ident("slice"), str_to_ident("Iter")],
-                util::param_ty_from_ident(
-                    str_to_ident("Box"),
-                    util::ty_from_ident(self.object_name())
-                )
-            ))),
-            P(util::create_block(
-                Vec::new(),
-                Some(P(util::create_method_call(
-                    str_to_ident("iter"),
-                    P(util::create_self_field_expr(str_to_ident("objects"))),
+            Some(quote!(impl DoubleEndedIterator<Item = &#boxed_object> + '_)),
+            quote! {
+                let dropped = if self.dropped.0.load(std::sync::atomic::Ordering::Acquire) {
+                    self.dropped.1.lock().unwrap().clone()
+                } else {
                     Vec::new()
-                )))
-            ))
+                };
+
+                self.objects.iter()
+                    .zip(self.owners.iter())
+                    .filter(move |&(_, &owner)| !dropped.contains(&#idx_name(owner, self.generations[owner])))
+                    .map(|(object, _)| object)
+            }
         )
     }
 
-    fn generate_fn_iter_mut_impl(&self) -> ImplItem {
+    fn generate_fn_iter_mut_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+
         util::impl_mut_method(
             str_to_ident("iter_mut"),
+            &[],
             Vec::new(),
-            Some(P(util::path_param_ty(
-                vec![str_to_ident("std"), str_to_ident("slice"), str_to_ident("IterMut")],
-                util::param_ty_from_ident(
-                    str_to_ident("Box"),
-                    util::ty_from_ident(self.object_name())
-                )
-            ))),
-            P(util::create_block(
-                Vec::new(),
-                Some(P(util::create_method_call(
-                    str_to_ident("iter_mut"),
-                    P(util::create_self_field_expr(str_to_ident("objects"))),
-                    Vec::new()
-                )))
-            ))
+            Some(quote!(std::slice::IterMut<'_, #boxed_object>)),
+            quote! {
+                self.remove_dropped();
+                self.objects.iter_mut()
+            }
+        )
+    }
+
+    fn generate_fn_object_idx_impl(&self) -> TokenStream {
+        let idx_name = self.idx_name();
+
+        // Returns the position in `objects` of the object with the given index, if it has not been
+        // removed. Objects whose guards were dropped are skipped until they can be removed.
+        util::impl_method_priv(
+            str_to_ident("object_idx"),
+            vec![util::create_arg(str_to_ident("idx"), quote!(#idx_name))],
+            Some(quote!(Option<usize>)),
+            quote! {
+                if self.dropped.0.load(std::sync::atomic::Ordering::Acquire) && self.dropped.1.lock().unwrap().contains(&idx) {
+                    return None;
+                }
+
+                match self.generations.get(idx.0) {
+                    Some(&generation) if generation == idx.1 => self.idxs[idx.0],
+                    _ => None
+                }
+            }
         )
     }
 
-    fn generate_fn_remove_impl(&self) -> ImplItem {
+    fn generate_fn_remove_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
+
+        // Handler entries are removed straight away, since the slot may be reused with a different priority
+        let handler_idxs = self.handlers.iter().map(|handler| {
+            let handler_idxs = util::idxs_ident(&handler.name);
+            let cfgs = handler.cfgs();
+            quote!(#(#cfgs)* self.#handler_idxs.retain(|&other| other != idx);)
+        });
+
         util::impl_mut_method(
             str_to_ident("remove"),
-            vec![util::create_arg(
-                str_to_ident("idx"),
-                P(util::ty_from_ident(self.idx_name()))
-            )],
-            Some(P(util::param_ty_from_ident(
-                str_to_ident("Option"),
-                util::param_ty_from_ident(
-                    str_to_ident("Box"),
-                    util::ty_from_ident(self.object_name())
-                )
-            ))),
-            P(util::create_block(
-                Vec::new(),
-                Some(P(util::create_method_call(
-                    str_to_ident("and_then"),
-                    P(util::create_method_call(
-                        str_to_ident("cloned"),
-                        P(util::create_method_call(
-                            str_to_ident("get"),
-                            P(util::create_self_field_expr(str_to_ident("idxs"))),
-                            vec![P(util::create_tuple_field_expr(
-                                P(util::create_var_expr(str_to_ident("idx"))),
-                                0
-                            ))],
-                        )),
-                        Vec::new()
-                    )),
-                    vec![P(util::create_closure_expr(
-                        vec![util::create_arg(
-                            str_to_ident("obj_idx"),
-                            P(util::param_ty_from_ident(
-                                str_to_ident("Option"),
-                                util::ty_from_ident(str_to_ident("usize"))
-                            ))
-                        )],
-                        P(util::create_block(
-                            Vec::new(),
-                            Some(P(util::create_method_call(
-                                str_to_ident("map"),
-                                P(util::create_var_expr(str_to_ident("obj_idx"))),
-                                vec![P(util::create_closure_expr(
-                                    vec![util::create_arg(
-                                        str_to_ident("obj_idx"),
-                                        P(util::ty_from_ident(str_to_ident("usize")))
-                                    )],
-                                    P(util::create_unsafe_block(
-                                        vec![
-                                            util::create_let_stmt(
-                                                str_to_ident("obj"),
-                                                Some(P(util::create_method_call(
-                                                    str_to_ident("swap_remove"),
-                                                    P(util::create_self_field_expr(str_to_ident("objects"))),
-                                                    vec![P(util::create_var_expr(str_to_ident("obj_idx")))]
-                                                )))
-                                            ),
-                                            util::create_stmt(P(util::create_assign_expr(
-                                                P(util::create_deref_expr(P(util::create_method_call(
-                                                    str_to_ident("unwrap"),
-                                                    P(util::create_method_call(
-                                                        str_to_ident("last_mut"),
-                                                        P(util::create_self_field_expr(str_to_ident("idxs"))),
-                                                        Vec::new()
-                                                    )),
-                                                    Vec::new()
-                                                )))),
-                                                P(util::create_call(
-                                                    P(util::create_var_expr(str_to_ident("Some"))),
-                                                    vec![P(util::create_var_expr(str_to_ident("obj_idx")))]
-                                                ))
-                                            ))),
-                                            util::create_stmt(P(util::create_assign_expr(
-                                                P(util::create_deref_expr(P(util::create_method_call(
-                                                    str_to_ident("get_unchecked_mut"),
-                                                    P(util::create_self_field_expr(str_to_ident("idxs"))),
-                                                    vec![P(util::create_tuple_field_expr(
-                                                        P(util::create_var_expr(str_to_ident("idx"))),
-                                                        0
-                                                    ))]
-                                                )))),
-                                                P(util::create_var_expr(str_to_ident("None")))
-                                            )))
-                                        ],
-                                        Some(P(util::create_var_expr(str_to_ident("obj"))))
-                                    ))
-                                ))]
-                            )))
-                        ))
-                    ))]
-                )))
-            ))
+            &[],
+            vec![util::create_arg(str_to_ident("idx"), quote!(#idx_name))],
+            Some(quote!(Option<#boxed_object>)),
+            quote! {
+                self.object_idx(idx).map(|obj_idx: usize| {
+                    let obj = self.objects.swap_remove(obj_idx);
+                    self.owners.swap_remove(obj_idx);
+
+                    // The last object was moved into the removed object's position
+                    if obj_idx < self.objects.len() {
+                        self.idxs[self.owners[obj_idx]] = Some(obj_idx);
+                    }
+
+                    self.idxs[idx.0] = None;
+                    self.generations[idx.0] = self.generations[idx.0].wrapping_add(1);
+                    self.free.push(idx.0);
+                    #(#handler_idxs)*
+                    obj
+                })
+            }
         )
     }
 
-    fn generate_fn_get_impl(&self) -> ImplItem {
+    fn generate_fn_get_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
+
         util::impl_method(
             str_to_ident("get"),
-            vec![util::create_arg(
-                str_to_ident("idx"),
-                P(util::ty_from_ident(self.idx_name()))
-            )],
-            Some(P(util::param_ty_from_ident(
-                str_to_ident("Option"),
-                util::ref_ty(P(util::param_ty_from_ident(
-                    str_to_ident("Box"),
-                    util::ty_from_ident(self.object_name())
-                )))
-            ))),
-            P(util::create_block(
-                Vec::new(),
-                Some(P(util::create_method_call(
-                    str_to_ident("and_then"),
-                    P(util::create_method_call(
-                        str_to_ident("cloned"),
-                        P(util::create_method_call(
-                            str_to_ident("get"),
-                            P(util::create_self_field_expr(str_to_ident("idxs"))),
-                            vec![P(util::create_tuple_field_expr(
-                                P(util::create_var_expr(str_to_ident("idx"))),
-                                0
-                            ))],
-                        )),
-                        Vec::new()
-                    )),
-                    vec![P(util::create_closure_expr(
-                        vec![util::create_arg(
-                            str_to_ident("obj_idx"),
-                            P(util::param_ty_from_ident(
-                                str_to_ident("Option"),
-                                util::ty_from_ident(str_to_ident("usize"))
-                            ))
-                        )],
-                        P(util::create_block(
-                            Vec::new(),
-                            Some(P(util::create_method_call(
-                                str_to_ident("map"),
-                                P(util::create_var_expr(str_to_ident("obj_idx"))),
-                                vec![P(util::create_closure_expr(
-                                    vec![util::create_arg(
-                                        str_to_ident("obj_idx"),
-                                        P(util::ty_from_ident(str_to_ident("usize")))
-                                    )],
-                                    P(util::create_unsafe_block(
-                                        Vec::new(),
-                                        Some(P(util::create_method_call(
-                                            str_to_ident("get_unchecked"),
-                                            P(util::create_self_field_expr(str_to_ident("objects"))),
-                                            vec![P(util::create_var_expr(str_to_ident("obj_idx")))]
-                                        )))
-                                    ))
-                                ))]
-                            )))
-                        ))
-                    ))]
-                )))
-            ))
+            &[],
+            vec![util::create_arg(str_to_ident("idx"), quote!(#idx_name))],
+            Some(quote!(Option<&#boxed_object>)),
+            quote! {
+                self.object_idx(idx).map(|obj_idx: usize| unsafe {
+                    self.objects.get_unchecked(obj_idx)
+                })
+            }
         )
     }
 
-    fn generate_fn_get_mut_impl(&self) -> ImplItem {
+    fn generate_fn_get_mut_impl(&self) -> TokenStream {
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
+
         util::impl_mut_method(
             str_to_ident("get_mut"),
-            vec![util::create_arg(
-                str_to_ident("idx"),
-                P(util::ty_from_ident(self.idx_name()))
-            )],
-            Some(P(util::param_ty_from_ident(
-                str_to_ident("Option"),
-                util::mut_ref_ty(P(util::param_ty_from_ident(
-                    str_to_ident("Box"),
-                    util::ty_from_ident(self.object_name())
-                )))
-            ))),
-            P(util::create_block(
-                Vec::new(),
-                Some(P(util::create_method_call(
-                    str_to_ident("and_then"),
-                    P(util::create_method_call(
-                        str_to_ident("cloned"),
-                        P(util::create_method_call(
-                            str_to_ident("get"),
-                            P(util::create_self_field_expr(str_to_ident("idxs"))),
-                            vec![P(util::create_tuple_field_expr(
-                                P(util::create_var_expr(str_to_ident("idx"))),
-                                0
-                            ))],
-                        )),
-                        Vec::new()
-                    )),
-                    vec![P(util::create_closure_expr(
-                        vec![util::create_arg(
-                            str_to_ident("obj_idx"),
-                            P(util::param_ty_from_ident(
-                                str_to_ident("Option"),
-                                util::ty_from_ident(str_to_ident("usize"))
-                            ))
-                        )],
-                        P(util::create_block(
-                            Vec::new(),
-                            Some(P(util::create_method_call(
-                                str_to_ident("map"),
-                                P(util::create_var_expr(str_to_ident("obj_idx"))),
-                                vec![P(util::create_closure_expr(
-                                    vec![util::create_arg(
-                                        str_to_ident("obj_idx"),
-                                        P(util::ty_from_ident(str_to_ident("usize")))
-                                    )],
-                                    P(util::create_unsafe_block(
-                                        Vec::new(),
-                                        Some(P(util::create_method_call(
-                                            str_to_ident("get_unchecked_mut"),
-                                            P(util::create_self_field_expr(str_to_ident("objects"))),
-                                            vec![P(util::create_var_expr(str_to_ident("obj_idx")))]
-                                        )))
-                                    ))
-                                ))]
-                            )))
-                        ))
-                    ))]
-                )))
-            ))
+            &[],
+            vec![util::create_arg(str_to_ident("idx"), quote!(#idx_name))],
+            Some(quote!(Option<&mut #boxed_object>)),
+            quote! {
+                self.object_idx(idx).map(|obj_idx: usize| unsafe {
+                    self.objects.get_unchecked_mut(obj_idx)
+                })
+            }
         )
     }
 
-    fn generate_impl(&self) -> Item {
+    fn generate_impl(&self) -> TokenStream {
         let mut fns = vec![
             self.generate_fn_new_impl(),
             self.generate_fn_add_impl(),
+            self.generate_fn_add_with_priority_impl(),
+            self.generate_fn_add_scoped_impl(),
+            self.generate_fn_remove_dropped_impl(),
             self.generate_fn_iter_impl(),
             self.generate_fn_iter_mut_impl(),
+            self.generate_fn_object_idx_impl(),
             self.generate_fn_remove_impl(),
             self.generate_fn_get_impl(),
             self.generate_fn_get_mut_impl(),
         ];
 
         for handler in self.handlers.iter() {
-            handler.generate_signal_impl(&mut fns);
+            handler.generate_signal_impl(&self.generics, &mut fns);
+            handler.generate_targeted_signal_impl(&self.generics, &self.idx_name(), &mut fns);
         }
 
+        fns.push(self.generate_fn_dispatch_impl());
+        fns.push(self.generate_fn_emit_impl());
+        self.generate_fn_queue_impls(&mut fns);
+        fns.push(self.generate_fn_process_events_impl());
+        self.generate_fn_connect_impls(&mut fns);
+        fns.push(self.generate_fn_disconnect_impl());
+
         util::create_impl(
-            self.name,
+            self.ty(),
+            &self.generics,
             None,
             fns
         )
     }
 
-    pub fn generate_object_impl(&self, thing: Ident, impls: &Vec<String>) -> Box<MacResult> {
+    fn generate_shared_struct(&self) -> TokenStream {
+        let ty = self.ty();
+
+        let item = util::create_struct(&self.vis, self.shared_name(), &self.generics, vec![
+            util::create_struct_field(str_to_ident("inner"), quote!(std::sync::Arc<std::sync::Mutex<#ty>>))
+        ]);
+
+        // Cloning shares the same system, and should not require the system's parameters to be Clone
+        let clone = util::create_impl(self.shared_ty(), &self.generics, Some(quote!(Clone)), vec![quote! {
+            fn clone(&self) -> Self {
+                Self { inner: self.inner.clone() }
+            }
+        }]);
+
+        quote! {
+            #item
+            #clone
+        }
+    }
+
+    fn generate_shared_impl(&self) -> TokenStream {
+        let ty = self.ty();
+        let boxed_object = self.boxed_object_ty();
+        let idx_name = self.idx_name();
+        let guard_name = self.guard_name();
+        let event_name = self.event_name();
+
+        let mut fns = vec![
+            util::impl_static_method(
+                str_to_ident("new"),
+                vec![util::create_arg(str_to_ident("system"), ty.clone())],
+                Some(quote!(Self)),
+                quote!(Self { inner: std::sync::Arc::new(std::sync::Mutex::new(system)) })
+            ),
+
+            // Panics if a slot panicked while the system was locked
+            util::impl_method(
+                str_to_ident("lock"),
+                &[],
+                Vec::new(),
+                Some(quote!(std::sync::MutexGuard<'_, #ty>)),
+                quote!(self.inner.lock().unwrap())
+            ),
+
+            util::impl_method(
+                str_to_ident("add"),
+                &[],
+                vec![util::create_arg(str_to_ident("object"), boxed_object.clone())],
+                Some(quote!(#idx_name)),
+                quote!(self.lock().add(object))
+            ),
+
+            util::impl_method(
+                str_to_ident("add_with_priority"),
+                &[],
+                vec![
+                    util::create_arg(str_to_ident("object"), boxed_object.clone()),
+                    util::create_arg(str_to_ident("priority"), quote!(i32))
+                ],
+                Some(quote!(#idx_name)),
+                quote!(self.lock().add_with_priority(object, priority))
+            ),
+
+            util::impl_method(
+                str_to_ident("add_scoped"),
+                &[],
+                vec![util::create_arg(str_to_ident("object"), boxed_object.clone())],
+                Some(quote!(#guard_name)),
+                quote!(self.lock().add_scoped(object))
+            ),
+
+            util::impl_method(
+                str_to_ident("remove"),
+                &[],
+                vec![util::create_arg(str_to_ident("idx"), quote!(#idx_name))],
+                Some(quote!(Option<#boxed_object>)),
+                quote!(self.lock().remove(idx))
+            ),
+
+            util::impl_method(
+                str_to_ident("emit"),
+                &[],
+                vec![util::create_arg(str_to_ident("event"), quote!(#event_name))],
+                None,
+                quote!(self.lock().emit(event);)
+            ),
+
+            util::impl_method(
+                str_to_ident("process_events"),
+                &[],
+                Vec::new(),
+                None,
+                quote!(self.lock().process_events();)
+            ),
+        ];
+
+        // Async signals are not forwarded, since the system would stay locked while they are awaited
+        for handler in self.handlers.iter() {
+            for func in handler.fns.iter().filter(|func| !func.asynchronous) {
+                let source_name = &func.source_name;
+                let arg_names: Vec<&Ident> = func.args.iter().map(|arg| &arg.name).collect();
+                let ret = func.ret.as_ref().map(|ty| func.aggregate.generate_ty(ty));
+
+                let attrs = handler.cfgs().into_iter().chain(func.attrs.iter());
+                let item = util::impl_method(
+                    source_name.clone(),
+                    &func.lifetimes(&self.generics),
+                    func.args.iter().map(|arg| arg.generate()).collect(),
+                    ret,
+                    quote!(self.lock().#source_name(#(#arg_names),*))
+                );
+
+                fns.push(quote!(#(#attrs)* #item));
+            }
+        }
+
+        for (handler, func) in self.queued_fns() {
+            let queue_fn_name = func.queue_fn_name();
+            let arg_names: Vec<&Ident> = func.args.iter().map(|arg| &arg.name).collect();
+
+            let attrs = handler.cfgs().into_iter().chain(func.attrs.iter());
+            let item = util::impl_method(
+                queue_fn_name.clone(),
+                &[],
+                func.args.iter().map(|arg| arg.generate()).collect(),
+                None,
+                quote!(self.lock().#queue_fn_name(#(#arg_names),*);)
+            );
+
+            fns.push(quote!(#(#attrs)* #item));
+        }
+
+        util::create_impl(self.shared_ty(), &self.generics, None, fns)
+    }
+
+    // An object implemented for every instantiation of a system with type parameters may only
+    // implement a handler for some of them, which the probe cannot see, so every handler must be listed
+    pub fn infers_handlers(&self, args: Option<&AngleBracketedGenericArguments>) -> bool {
+        args.is_some() || self.generics.params.iter().all(|param| matches!(*param, GenericParam::Lifetime(_)))
+    }
+
+    pub fn generate_object_impl(&self, thing: Ident, prefix: &TokenStream, args: Option<&AngleBracketedGenericArguments>, impls: &[String]) -> TokenStream {
+        // Without arguments the object is implemented for every instantiation of the system,
+        // otherwise only for the given one, generic over any lifetimes it uses
+        let (generics, args) = match args {
+            Some(args) => {
+                let mut lifetimes: Vec<Lifetime> = args.args.iter().filter_map(|arg| match *arg {
+                    GenericArgument::Lifetime(ref lifetime) if lifetime.ident != "static" => Some(lifetime.clone()),
+                    _ => None
+                }).collect();
+
+                for lifetime in util::collect_lifetimes(args.args.iter().filter_map(|arg| match *arg {
+                    GenericArgument::Type(ref ty) => Some(ty),
+                    _ => None
+                })) {
+                    if !lifetimes.contains(&lifetime) {
+                        lifetimes.push(lifetime);
+                    }
+                }
+
+                (parse_quote!(<#(#lifetimes),*>), quote!(#args))
+            },
+
+            None => {
+                let (_, ty_generics, _) = self.generics.split_for_impl();
+                (self.generics.clone(), quote!(#ty_generics))
+            }
+        };
+
         let mut items = Vec::new();
 
         for handler in self.handlers.iter() {
-            items.extend_from_slice(&[
+            let implemented = impls.contains(&handler.name.to_string());
+            let cfgs = handler.cfgs();
+
+            let handler_name = &handler.name;
+            let name = quote!(#prefix #handler_name);
+
+            items.extend([
                 util::impl_method_priv(
-                    util::as_ident(handler.name),
+                    util::as_ident(handler_name),
                     Vec::new(),
-                    Some(P(util::param_ty_from_ident(
-                        str_to_ident("Option"),
-                        util::ref_ty_from_ident(handler.name)
-                    ))),
-                    P(util::create_block(
-                        Vec::new(),
-                        Some(P(if impls.contains(&format!("{}", handler.name)) {
-                            util::create_call(
-                                P(util::create_var_expr(str_to_ident("Some"))),
-                                vec![P(util::create_cast_expr(
-                                        P(util::create_var_expr(str_to_ident("self"))),
-                                        P(util::ref_ty_from_ident(handler.name))
-                                ))]
-                            )
-                        } else {
-                            util::create_var_expr(str_to_ident("None"))
-                        })),
-                    ))
+                    Some(quote!(Option<&dyn #name #args>)),
+                    if implemented {
+                        quote!(Some(self as &dyn #name #args))
+                    } else {
+                        self.generate_inferred_as_self(&name, &generics, &args, false)
+                    }
                 ),
 
                 util::impl_mut_method_priv(
-                    util::as_mut_ident(handler.name),
+                    util::as_mut_ident(handler_name),
                     Vec::new(),
-                    Some(P(util::param_ty_from_ident(
-                        str_to_ident("Option"),
-                        util::mut_ref_ty_from_ident(handler.name)
-                    ))),
-                    P(util::create_block(
-                        Vec::new(),
-                        Some(P(if impls.contains(&format!("{}", handler.name)) {
-                            util::create_call(
-                                P(util::create_var_expr(str_to_ident("Some"))),
-                                vec![P(util::create_cast_expr(
-                                        P(util::create_var_expr(str_to_ident("self"))),
-                                        P(util::mut_ref_ty_from_ident(handler.name))
-                                ))]
-                            )
-                        } else {
-                            util::create_var_expr(str_to_ident("None"))
-                        })),
-                    ))
+                    Some(quote!(Option<&mut dyn #name #args>)),
+                    if implemented {
+                        quote!(Some(self as &mut dyn #name #args))
+                    } else {
+                        self.generate_inferred_as_self(&name, &generics, &args, true)
+                    }
                 )
-            ]);
+            ].into_iter().map(|item| quote!(#(#cfgs)* #item)));
         }
 
-        MacEager::items(SmallVector::one(P(util::create_impl(
-            thing,
-            Some(self.object_name()),
+        let object_name = self.object_name();
+        let cfgs = self.cfgs();
+
+        let item = util::create_impl(
+            quote!(#thing),
+            &generics,
+            Some(quote!(#prefix #object_name #args)),
             items
-        ))))
+        );
+
+        quote! {
+            #(#cfgs)*
+            #item
+        }
     }
 
-    pub fn generate_ast(&self) -> Box<MacResult> {
-        let mut items: Vec<P<Item>> = self.handlers.iter().map(|handler| P(handler.generate())).collect();
+    // Handlers which were not listed are converted only if the object implements them. The probe's
+    // `Found` impl is chosen by method resolution when the bound holds, and the autoref'd `Missing`
+    // impl otherwise, which works on stable since the object's type is known at the call site.
+    fn generate_inferred_as_self(&self, name: &TokenStream, generics: &Generics, args: &TokenStream, mutable: bool) -> TokenStream {
+        let (lifetimes, params): (Vec<&GenericParam>, Vec<&GenericParam>) = generics.params.iter()
+            .partition(|param| matches!(**param, GenericParam::Lifetime(_)));
+        let where_clause = &generics.where_clause;
+
+        let (reference, field, probe, found) = if mutable {
+            (
+                quote!(&'__ref mut),
+                quote!(std::cell::Cell<Option<&'__ref mut __Object>>),
+                quote!(std::cell::Cell::new(Some(self))),
+                quote!(self.0.take().map(|object| object as &'__ref mut (dyn #name #args + '__dyn)))
+            )
+        } else {
+            (
+                quote!(&'__ref),
+                quote!(Option<&'__ref __Object>),
+                quote!(Some(self)),
+                quote!(self.0.map(|object| object as &'__ref (dyn #name #args + '__dyn)))
+            )
+        };
+
+        quote! {
+            struct Probe<'__ref, __Object, __Dyn: ?Sized>(#field, std::marker::PhantomData<&'__ref __Dyn>);
+
+            trait Found<'__ref, __Dyn: ?Sized> {
+                fn found(&self) -> Option<#reference __Dyn>;
+            }
+
+            trait Missing<'__ref, __Dyn: ?Sized> {
+                fn found(&self) -> Option<#reference __Dyn>;
+            }
+
+            impl<'__ref, '__dyn, #(#lifetimes,)* #(#params,)* __Object: #name #args + '__dyn> Found<'__ref, dyn #name #args + '__dyn>
+                for Probe<'__ref, __Object, dyn #name #args + '__dyn> #where_clause
+            {
+                fn found(&self) -> Option<#reference (dyn #name #args + '__dyn)> {
+                    #found
+                }
+            }
+
+            impl<'__ref, __Object, __Dyn: ?Sized> Missing<'__ref, __Dyn> for &Probe<'__ref, __Object, __Dyn> {
+                fn found(&self) -> Option<#reference __Dyn> {
+                    None
+                }
+            }
+
+            (&Probe(#probe, std::marker::PhantomData::<&dyn #name #args>)).found()
+        }
+    }
+
+    // Exports the system's definition as a macro, so objects can be implemented in other modules and crates
+    pub fn generate_export(&self, source: TokenStream, id: u64) -> TokenStream {
+        let source = util::dollar_crate(source);
+        let system_macro = util::system_macro_ident(&self.name);
+        let export_macro = str_to_ident(&format!("__handlers_define_{}_{:016x}", self.name, id));
+        let vis = &self.vis;
+        let cfgs = self.cfgs();
+
+        quote! {
+            #(#cfgs)*
+            #[doc(hidden)]
+            #[macro_export]
+            macro_rules! #export_macro {
+                ($($object:tt)*) => {
+                    ::handlers::handlers_impl_object! { @ { #source } $($object)* }
+                };
+            }
+
+            #(#cfgs)*
+            #[doc(hidden)]
+            #[allow(unused_imports)]
+            #vis use #export_macro as #system_macro;
+        }
+    }
+
+    pub fn generate_ast(&self) -> TokenStream {
+        let mut items: Vec<TokenStream> = self.handlers.iter().map(|handler| handler.generate(&self.generics)).collect();
         items.extend_from_slice(&[
-            P(self.generate_object_trait()),
-            P(self.generate_idx_struct()),
-            P(self.generate_struct()),
-            P(self.generate_impl())
+            self.generate_object_trait(),
+            self.generate_idx_struct(),
+            self.generate_guard_struct(),
+            self.generate_connection_struct(),
+            self.generate_propagation_enum(),
+            self.generate_event_enum(),
+            self.generate_queue_struct(),
+            self.generate_queue_impl(),
+            self.generate_struct(),
+            self.generate_impl()
         ]);
 
-        MacEager::items(SmallVector::many(items))
+        // Systems whose objects are required to be Send can be shared between threads
+        if self.is_send() {
+            items.push(self.generate_shared_struct());
+            items.push(self.generate_shared_impl());
+        }
+
+        // A system gated by `#[cfg]` takes every generated item with it
+        let cfgs = self.cfgs();
+        let items = items.into_iter().map(|item| quote!(#(#cfgs)* #item));
+        quote!(#(#items)*)
     }
 }
 
 impl HandlerInfo {
-    pub fn new(name: Ident) -> HandlerInfo {
+    pub fn new(vis: Visibility, name: Ident) -> HandlerInfo {
         HandlerInfo {
-            name: name,
+            attrs: Vec::new(),
+            vis,
+            name,
             reqs: Vec::new(),
             fns: Vec::new()
         }
     }
-    
+
+    pub fn set_attrs(&mut self, attrs: Vec<Attribute>) {
+        self.attrs = attrs;
+    }
+
     pub fn add_requirement(&mut self, req: Ident) {
         self.reqs.push(req);
     }
@@ -633,204 +1243,765 @@ impl HandlerInfo {
         self.fns.push(function);
     }
 
-    pub fn generate_as_self(&self) -> TraitItem {
-        util::create_trait_method(
-            util::as_ident(self.name),
+    pub fn cfgs(&self) -> Vec<&Attribute> {
+        util::cfg_attrs(&self.attrs)
+    }
+
+    pub fn generate_as_self(&self, generics: &Generics) -> TokenStream {
+        let name = &self.name;
+        let (_, ty_generics, _) = generics.split_for_impl();
+        let cfgs = self.cfgs();
+
+        let item = util::create_trait_method(
+            util::as_ident(name),
+            &[],
             Vec::new(),
-            Some(P(util::param_ty_from_ident(
-                str_to_ident("Option"),
-                util::ref_ty_from_ident(self.name)
-            )))
-        )
+            Some(quote!(Option<&dyn #name #ty_generics>))
+        );
+
+        quote!(#(#cfgs)* #item)
     }
 
-    pub fn generate_as_self_mut(&self) -> TraitItem {
-        util::create_mut_trait_method(
-            util::as_mut_ident(self.name),
+    pub fn generate_as_self_mut(&self, generics: &Generics) -> TokenStream {
+        let name = &self.name;
+        let (_, ty_generics, _) = generics.split_for_impl();
+        let cfgs = self.cfgs();
+
+        let item = util::create_mut_trait_method(
+            util::as_mut_ident(name),
+            &[],
             Vec::new(),
-            Some(P(util::param_ty_from_ident(
-                str_to_ident("Option"),
-                util::mut_ref_ty_from_ident(self.name)
-            )))
-        )
+            Some(quote!(Option<&mut dyn #name #ty_generics>))
+        );
+
+        quote!(#(#cfgs)* #item)
     }
 
-    pub fn generate(&self) -> Item {
-        util::create_trait(
-            self.name,
-            &self.reqs,
-            &self.fns.iter().map(|function| function.generate()).collect()
-        )
+    pub fn generate(&self, generics: &Generics) -> TokenStream {
+        let mut reqs = self.reqs.clone();
+
+        // Slots of parallel signals are called from other threads. Trait objects cannot carry a bound
+        // for some methods only, so the whole handler trait requires it, not just the parallel signals
+        if self.fns.iter().any(|func| func.parallel && !func.immutable) {
+            reqs.push(str_to_ident("Send"));
+        }
+
+        if self.fns.iter().any(|func| func.parallel && func.immutable) {
+            reqs.push(str_to_ident("Sync"));
+        }
+
+        let attrs = &self.attrs;
+        let item = util::create_trait(
+            &self.vis,
+            self.name.clone(),
+            generics,
+            &reqs,
+            &self.fns.iter().map(|function| function.generate(generics)).collect()
+        );
+
+        quote! {
+            #(#attrs)*
+            #item
+        }
     }
 
-    pub fn generate_signal_impl(&self, items: &mut Vec<ImplItem>) {
+    pub fn generate_signal_impl(&self, generics: &Generics, items: &mut Vec<TokenStream>) {
+        let [results, result, idx, i, slot] = util::local_idents(["results", "result", "idx", "i", "slot"]);
+
+        let handler_idxs = util::idxs_ident(&self.name);
+        let as_self = util::as_ident(&self.name);
+        let as_mut = util::as_mut_ident(&self.name);
+
         for func in self.fns.iter() {
-            let loop_block = util::create_block(
-                vec![
-                    // if i > len() { return }
-                    util::create_stmt(P(util::create_if_expr(
-                        P(util::create_binop_expr(
-                            P(util::create_var_expr(str_to_ident("i"))),
-                            BinOpKind::Ge,
-                            P(util::create_method_call(
-                                str_to_ident("len"),
-                                P(util::create_self_field_expr(util::idxs_ident(self.name))),
-                                Vec::new()
-                            ))
-                        )),
-                        P(util::create_return_block(None)),
-                        None
-                    ))),
-
-                    // let idx = *handler_idxs.get_unchecked(i);
-                    util::create_let_stmt(
-                        str_to_ident("idx"),
-                        Some(P(util::create_deref_expr(P(util::create_method_call(
-                            str_to_ident("get_unchecked"),
-                            P(util::create_self_field_expr(util::idxs_ident(self.name))),
-                            vec![P(util::create_var_expr(str_to_ident("i")))]
-                        )))))
-                    ),
-
-                    util::create_let_stmt(
-                        str_to_ident("idx"),
-                        Some(P(util::create_deref_expr(P(util::create_method_call(
-                            str_to_ident("get_unchecked"),
-                            P(util::create_self_field_expr(str_to_ident("idxs"))),
-                            vec![P(util::create_var_expr(str_to_ident("idx")))]
-                        )))))
-                    ),
-
-                    util::create_stmt(P(util::create_if_let_expr(
-                        P(util::create_tuple_struct_pat(
-                            str_to_ident("Some"),
-                            vec![str_to_ident("idx")]
-                        )),
-                        P(util::create_var_expr(str_to_ident("idx"))),
-                        P(util::create_block(
-                            vec![
-                                util::create_stmt(P(util::create_method_call(
-                                    func.dest_name,
-                                    P(util::create_method_call(
-                                        str_to_ident("unwrap"),
-                                        P(util::create_method_call(
-                                            util::as_mut_ident(self.name),
-                                            P(util::create_method_call(
-                                                str_to_ident("get_unchecked_mut"),
-                                                P(util::create_self_field_expr(str_to_ident("objects"))),
-                                                vec![P(util::create_var_expr(str_to_ident("idx")))]
-                                            )),
-                                            Vec::new()
-                                        )),
-                                        Vec::new(),
-                                    )),
-                                    func.args.iter().map(|arg| P(util::create_var_expr(arg.name))).collect()
-                                ))),
-
-                                util::create_stmt(P(util::create_assignop_expr(
-                                    P(util::create_var_expr(str_to_ident("i"))),
-                                    BinOpKind::Add,
-                                    P(util::create_num_expr(1))
-                                )))
-                            ],
-                            None
-                        )),
-                        Some(P(util::create_block_expr(P(util::create_block(
-                            vec![util::create_stmt(P(util::create_method_call(
-                                str_to_ident("swap_remove"),
-                                P(util::create_self_field_expr(util::idxs_ident(self.name))),
-                                vec![P(util::create_var_expr(str_to_ident("i")))]
-                            )))],
-                            None
-                        ))))),
-                    )))
-                ],
-                None
-            );
+            let dest_name = &func.dest_name;
+            let arg_names: Vec<TokenStream> = func.args.iter().map(|arg| arg.generate_pass()).collect();
 
-            items.push(util::impl_mut_method(
-                func.source_name,
-                func.args.iter().map(|arg| arg.generate()).collect(),
-                None,
-                P(util::create_unsafe_block(
-                    vec![
-                        // let mut i = 0;
-                        util::create_let_mut_stmt(
-                            str_to_ident("i"),
-                            Some(P(util::create_num_expr(0)))
-                        ),
-
-                        // loop { .. }
-                        util::create_stmt(P(util::create_loop_expr(P(loop_block)))),
-                    ],
-                    None
-                ))
-            ));
+            let queue = func.queue.as_ref().map(|_| quote!(&mut self.queue,));
+            let call = if func.immutable {
+                quote!(self.objects.get_unchecked(#idx).#as_self().unwrap().#dest_name(#(#arg_names),*))
+            } else {
+                quote!(self.objects.get_unchecked_mut(#idx).#as_mut().unwrap().#dest_name(#queue #(#arg_names),*))
+            };
+
+            // Async slots are awaited one at a time, in the same order as any other signal
+            let call = if func.asynchronous {
+                quote!(#call.await)
+            } else {
+                call
+            };
+
+            // Connected closures are called after every object, in the order they were connected
+            let connections = func.connections_ident();
+            let connection_call = if func.immutable {
+                quote!(#slot(#(#arg_names),*))
+            } else {
+                quote!(#slot(#queue #(#arg_names),*))
+            };
+
+            let (init, call, connection_call, ret, finish) = if let Some(ref ty) = func.ret {
+                let accumulate = func.aggregate.generate_accumulate(ty);
+
+                (
+                    Some(func.aggregate.generate_init()),
+                    quote! {
+                        let #result = #call;
+                        #accumulate
+                    },
+                    quote! {
+                        let #result = #connection_call;
+                        #accumulate
+                    },
+                    Some(func.aggregate.generate_ty(ty)),
+                    quote!(#results)
+                )
+            } else {
+                (None, quote!(#call;), quote!(#connection_call;), None, TokenStream::new())
+            };
+
+            let init = init.map(|init| quote!(let mut #results = #init;));
+
+            let (connected, connected_mut) = if func.asynchronous {
+                (None, None)
+            } else {
+                (
+                    Some(quote! {
+                        for (_, #slot) in self.#connections.iter() {
+                            #connection_call
+                        }
+                    }),
+                    Some(quote! {
+                        for (_, #slot) in self.#connections.iter_mut() {
+                            #connection_call
+                        }
+                    })
+                )
+            };
+
+            let attrs = self.cfgs().into_iter().chain(func.attrs.iter());
+            let args = func.args.iter().map(|arg| arg.generate()).collect();
+
+            let item = if func.parallel {
+                self.generate_parallel_signal(generics, func)
+            } else if func.immutable {
+                func.impl_signal_method(
+                    func.source_name.clone(),
+                    generics,
+                    args,
+                    ret,
+                    quote! {
+                        #init
+
+                        for &#idx in self.#handler_idxs.iter() {
+                            if let Some(#idx) = self.object_idx(#idx) {
+                                unsafe {
+                                    #call
+                                }
+                            }
+                        }
+
+                        #connected
+                        #finish
+                    }
+                )
+            } else {
+                func.impl_signal_method(
+                    func.source_name.clone(),
+                    generics,
+                    args,
+                    ret,
+                    quote! {
+                        unsafe {
+                            let mut #i = 0;
+                            #init
+
+                            loop {
+                                if #i >= self.#handler_idxs.len() {
+                                    #connected_mut
+                                    return #finish
+                                }
+
+                                let #idx = *self.#handler_idxs.get_unchecked(#i);
+
+                                if let Some(#idx) = self.object_idx(#idx) {
+                                    #call
+                                    #i += 1;
+                                } else {
+                                    self.#handler_idxs.remove(#i);
+                                }
+                            }
+                        }
+                    }
+                )
+            };
+
+            items.push(quote!(#(#attrs)* #item));
+
+            if func.asynchronous && func.aggregate != Aggregate::Propagate {
+                let attrs = self.cfgs().into_iter().chain(func.attrs.iter());
+                let item = self.generate_concurrent_signal(generics, func);
+                items.push(quote!(#(#attrs)* #item));
+            }
         }
     }
 
-    pub fn generate_add_check(&self) -> Expr {
-        util::create_if_expr(
-            P(util::create_method_call(
-                str_to_ident("is_some"),
-                P(util::create_method_call(
-                    util::as_ident(self.name),
-                    P(util::create_var_expr(str_to_ident("object"))),
-                    Vec::new()
-                )),
-                Vec::new()
-            )),
+    // Polls the futures of every slot together, for slots which wait on something other than the
+    // system. Results are aggregated in the same order as any other signal.
+    fn generate_concurrent_signal(&self, generics: &Generics, func: &HandlerFnInfo) -> TokenStream {
+        let [
+            positions, idx, futures, pos, objects, outputs, future, output, polled, cx, pending, result
+        ] = util::local_idents([
+            "positions", "idx", "futures", "pos", "objects", "outputs", "future", "output", "polled", "cx", "pending", "result"
+        ]);
 
-            P(util::create_block(
-                vec![
-                    util::create_stmt(P(util::create_method_call(
-                        str_to_ident("push"),
-                        P(util::create_self_field_expr(util::idxs_ident(self.name))),
-                        vec![
-                            P(util::create_var_expr(str_to_ident("idx")))
-                        ]
-                    ))),
-                ],
-                None
-            )),
+        let handler_idxs = util::idxs_ident(&self.name);
+        let as_self = util::as_ident(&self.name);
+        let as_mut = util::as_mut_ident(&self.name);
+        let dest_name = &func.dest_name;
+        let arg_names: Vec<TokenStream> = func.args.iter().map(|arg| arg.generate_pass()).collect();
 
-            None
-        )
+        let (ret, finish) = func.aggregate.generate_outputs(func.ret.as_ref());
+
+        let create_futures = if func.immutable {
+            quote! {
+                let mut #futures: Vec<_> = #positions.into_iter()
+                    .map(|#pos| Some(self.objects[#pos].#as_self().unwrap().#dest_name(#(#arg_names),*)))
+                    .collect();
+            }
+        } else {
+            quote! {
+                let mut #objects: Vec<_> = self.objects.iter_mut().map(Some).collect();
+                let mut #futures: Vec<_> = #positions.into_iter()
+                    .map(|#pos| Some(#objects[#pos].take().unwrap().#as_mut().unwrap().#dest_name(#(#arg_names),*)))
+                    .collect();
+            }
+        };
+
+        let block = quote! {
+            let #positions: Vec<usize> = self.#handler_idxs.iter().filter_map(|&#idx| self.object_idx(#idx)).collect();
+            #create_futures
+
+            let mut #outputs: Vec<_> = #futures.iter().map(|_| None).collect();
+
+            std::future::poll_fn(|#cx| {
+                let mut #pending = false;
+
+                for (#future, #output) in #futures.iter_mut().zip(#outputs.iter_mut()) {
+                    if let Some(ref mut #polled) = *#future {
+                        match std::future::Future::poll(#polled.as_mut(), #cx) {
+                            std::task::Poll::Ready(#result) => {
+                                *#output = Some(#result);
+                                *#future = None;
+                            },
+
+                            std::task::Poll::Pending => #pending = true
+                        }
+                    }
+                }
+
+                if #pending {
+                    std::task::Poll::Pending
+                } else {
+                    std::task::Poll::Ready(())
+                }
+            }).await;
+
+            #finish
+        };
+
+        let name = util::ident_append(&func.source_name, &str_to_ident("_concurrent"));
+        func.impl_signal_method(name, generics, func.args.iter().map(|arg| arg.generate()).collect(), ret, block)
+    }
+
+    // Slots are split into one chunk per available core, each but the first on a scoped thread
+    // spawned for this call. Each chunk's results are aggregated in order afterwards, so results
+    // are in the same order as a sequential signal.
+    fn generate_parallel_signal(&self, generics: &Generics, func: &HandlerFnInfo) -> TokenStream {
+        let [
+            positions, idx, handlers, objects, pos, chunk, threads, chunks,
+            first, outputs, scope, workers, handler, worker, err, slot
+        ] = util::local_idents([
+            "positions", "idx", "handlers", "objects", "pos", "chunk", "threads", "chunks",
+            "first", "outputs", "scope", "workers", "handler", "worker", "err", "slot"
+        ]);
+
+        let handler_idxs = util::idxs_ident(&self.name);
+        let as_self = util::as_ident(&self.name);
+        let as_mut = util::as_mut_ident(&self.name);
+        let dest_name = &func.dest_name;
+        let connections = func.connections_ident();
+
+        let arg_names: Vec<&Ident> = func.args.iter().map(|arg| &arg.name).collect();
+        let pass: Vec<TokenStream> = func.args.iter().map(|arg| {
+            let name = &arg.name;
+
+            match arg.pass {
+                ArgPass::Value => quote!(*#name),
+                ArgPass::Ref => quote!(#name),
+                ArgPass::Clone => quote!(#name.clone())
+            }
+        }).collect();
+
+        let (ret, finish) = func.aggregate.generate_outputs(func.ret.as_ref());
+
+        let (collect_handlers, split_chunks, iter, par_iter, par_trait) = if func.immutable {
+            (
+                quote! {
+                    let #handlers: Vec<_> = #positions.into_iter()
+                        .map(|#pos| self.objects[#pos].#as_self().unwrap())
+                        .collect();
+                },
+                quote!(#handlers.chunks(#chunk)),
+                quote!(iter),
+                quote!(par_iter),
+                quote!(IntoParallelRefIterator)
+            )
+        } else {
+            (
+                quote! {
+                    let mut #objects: Vec<_> = self.objects.iter_mut().map(Some).collect();
+                    let mut #handlers: Vec<_> = #positions.into_iter()
+                        .map(|#pos| #objects[#pos].take().unwrap().#as_mut().unwrap())
+                        .collect();
+                },
+                quote!(#handlers.chunks_mut(#chunk)),
+                quote!(iter_mut),
+                quote!(par_iter_mut),
+                quote!(IntoParallelRefMutIterator)
+            )
+        };
+
+        // With the `rayon` feature slots are called on rayon's global pool, which keeps the order
+        // of their results, instead of spawning threads on every call
+        let call = if cfg!(feature = "rayon") {
+            quote! {
+                let mut #outputs: Vec<Vec<_>> = vec![{
+                    use ::rayon::iter::{#par_trait, ParallelIterator};
+                    #handlers.#par_iter().map(|#handler| #handler.#dest_name(#(#pass),*)).collect()
+                }];
+            }
+        } else {
+            quote! {
+                let #threads = std::thread::available_parallelism().map_or(1, |#threads| #threads.get());
+                let #chunk = std::cmp::max(#handlers.len().div_ceil(#threads), 1);
+
+                let mut #chunks = #split_chunks;
+                let #first = #chunks.next();
+
+                let mut #outputs: Vec<Vec<_>> = std::thread::scope(|#scope| {
+                    let #workers: Vec<_> = #chunks.map(|#handlers| #scope.spawn(move || {
+                        #handlers.#iter().map(|#handler| #handler.#dest_name(#(#pass),*)).collect::<Vec<_>>()
+                    })).collect();
+
+                    // The first chunk is called on this thread, so no thread is spawned for a single chunk
+                    let mut #outputs = vec![#first.into_iter().flat_map(|#handlers| #handlers.#iter()).map(|#handler| #handler.#dest_name(#(#pass),*)).collect()];
+
+                    #outputs.extend(#workers.into_iter()
+                        .map(|#worker| #worker.join().unwrap_or_else(|#err| std::panic::resume_unwind(#err))));
+
+                    #outputs
+                });
+            }
+        };
+
+        let block = quote! {
+            let #positions: Vec<usize> = self.#handler_idxs.iter().filter_map(|&#idx| self.object_idx(#idx)).collect();
+            #collect_handlers
+
+            #(let #arg_names = &#arg_names;)*
+            #call
+
+            // Connected closures are called on this thread once every object has been called
+            #outputs.push(self.#connections.#iter().map(|(_, #slot)| #slot(#(#pass),*)).collect());
+
+            #finish
+        };
+
+        let args = func.args.iter().map(|arg| arg.generate()).collect();
+
+        if func.immutable {
+            util::impl_method(func.source_name.clone(), &func.lifetimes(generics), args, ret, block)
+        } else {
+            util::impl_mut_method(func.source_name.clone(), &func.lifetimes(generics), args, ret, quote! {
+                self.remove_dropped();
+                #block
+            })
+        }
+    }
+
+    pub fn generate_targeted_signal_impl(&self, generics: &Generics, idx_name: &Ident, items: &mut Vec<TokenStream>) {
+        let [target, obj_idx, handler, future] = util::local_idents(["target", "obj_idx", "handler", "future"]);
+
+        let as_self = util::as_ident(&self.name);
+        let as_mut = util::as_mut_ident(&self.name);
+
+        for func in self.fns.iter() {
+            let dest_name = &func.dest_name;
+
+            let mut args = vec![util::create_arg(target.clone(), quote!(#idx_name))];
+            args.extend(func.args.iter().map(|arg| arg.generate()));
+
+            // Only one slot is called, so arguments never need to be cloned
+            let arg_names: Vec<TokenStream> = func.args.iter().map(|arg| match arg.pass {
+                ArgPass::Ref => arg.generate_pass(),
+                ArgPass::Value | ArgPass::Clone => {
+                    let name = &arg.name;
+                    quote!(#name)
+                }
+            }).collect();
+
+            let queue = func.queue.as_ref().map(|_| quote!(&mut self.queue,));
+            let call = if func.immutable {
+                quote! {
+                    self.object_idx(#target)
+                        .and_then(|#obj_idx| self.objects[#obj_idx].#as_self())
+                        .map(|#handler| #handler.#dest_name(#(#arg_names),*))
+                }
+            } else {
+                quote! {
+                    self.object_idx(#target)
+                        .and_then(|#obj_idx| self.objects[#obj_idx].#as_mut())
+                        .map(|#handler| #handler.#dest_name(#queue #(#arg_names),*))
+                }
+            };
+
+            let call = if func.asynchronous {
+                quote! {
+                    match #call {
+                        Some(#future) => Some(#future.await),
+                        None => None
+                    }
+                }
+            } else {
+                call
+            };
+
+            let (ret, block) = if let Some(ref ty) = func.ret {
+                (quote!(Option<#ty>), call)
+            } else {
+                (quote!(bool), quote!(#call.is_some()))
+            };
+
+            let attrs = self.cfgs().into_iter().chain(func.attrs.iter());
+            let name = util::ident_append(&func.source_name, &str_to_ident("_to"));
+            let item = func.impl_signal_method(name, generics, args, Some(ret), block);
+
+            items.push(quote!(#(#attrs)* #item));
+        }
+    }
+
+    pub fn generate_add_check(&self) -> TokenStream {
+        let handler_idxs = util::idxs_ident(&self.name);
+        let as_self = util::as_ident(&self.name);
+        let cfgs = self.cfgs();
+
+        // Handlers are kept sorted by descending priority, with equal priorities in insertion order
+        quote! {
+            #(#cfgs)*
+            if object.#as_self().is_some() {
+                let pos = self.#handler_idxs.partition_point(|&other| self.priorities[other.0] >= priority);
+                self.#handler_idxs.insert(pos, idx);
+            }
+        }
     }
 }
 
 impl HandlerFnInfo {
     pub fn new(source: Ident, dest: Ident, args: Vec<HandlerFnArg>) -> HandlerFnInfo {
         HandlerFnInfo {
+            attrs: Vec::new(),
             source_name: source,
             dest_name: dest,
-            args: args
+            args,
+            ret: None,
+            aggregate: Aggregate::Collect,
+            queue: None,
+            default: None,
+            immutable: false,
+            parallel: false,
+            asynchronous: false,
+            send_future: false
         }
     }
 
-    pub fn generate(&self) -> TraitItem {
-        util::create_mut_trait_method(
-            self.dest_name,
-            self.args.iter().map(|arg| arg.generate()).collect(),
+    pub fn set_attrs(&mut self, attrs: Vec<Attribute>) {
+        self.attrs = attrs;
+    }
+
+    pub fn cfgs(&self) -> Vec<&Attribute> {
+        util::cfg_attrs(&self.attrs)
+    }
+
+    pub fn set_queue(&mut self, queue: Type) {
+        self.queue = Some(queue);
+    }
+
+    pub fn set_immutable(&mut self) {
+        self.immutable = true;
+    }
+
+    pub fn set_parallel(&mut self) {
+        self.parallel = true;
+    }
+
+    pub fn set_async(&mut self, send: bool) {
+        self.asynchronous = true;
+        self.send_future = send;
+    }
+
+    pub fn connections_ident(&self) -> Ident {
+        util::ident_append(&self.source_name, &str_to_ident("_connections"))
+    }
+
+    pub fn set_default(&mut self, block: Block) {
+        self.default = Some(block);
+    }
+
+    // The body used for slots marked `#[default]`, which do nothing
+    pub fn default_block(&self) -> Block {
+        let value = match (&self.ret, self.aggregate) {
+            (None, _) => TokenStream::new(),
+            (Some(ty), Aggregate::Propagate) => quote!(#ty::Continue),
+            (Some(_), _) => quote!(Default::default())
+        };
+
+        if self.asynchronous {
+            parse_quote!({ Box::pin(async { #value }) })
+        } else {
+            parse_quote!({ #value })
+        }
+    }
+
+    // Only signals that own their arguments can be stored in the system's event queue, and
+    // only signals which are not async can be sent when it is processed
+    pub fn is_queueable(&self) -> bool {
+        !self.asynchronous && self.args.iter().all(|arg| util::is_owned(&arg.ty))
+    }
+
+    // The future returned by async slots, which may borrow the object
+    fn future_ty(&self) -> TokenStream {
+        let ty = self.ret.as_ref().map_or(quote!(()), |ty| quote!(#ty));
+
+        // Futures of `Send` systems are also `Send`, so their signals can be spawned
+        let send = if self.send_future { quote!(+ Send) } else { quote!() };
+        quote!(std::pin::Pin<Box<dyn std::future::Future<Output = #ty> #send + '_>>)
+    }
+
+    // Signal methods take the same receiver as their slots, and are async if their slots are
+    fn impl_signal_method(&self, name: Ident, generics: &Generics, args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+        let lifetimes = self.lifetimes(generics);
+
+        // Objects whose guards were dropped stop receiving signals once the system can remove them
+        let block = if self.immutable {
+            block
+        } else {
+            quote! {
+                self.remove_dropped();
+                #block
+            }
+        };
+
+        match (self.immutable, self.asynchronous) {
+            (true, false) => util::impl_method(name, &lifetimes, args, ret, block),
+            (false, false) => util::impl_mut_method(name, &lifetimes, args, ret, block),
+            (true, true) => util::impl_async_method(name, &lifetimes, args, ret, block),
+            (false, true) => util::impl_async_mut_method(name, &lifetimes, args, ret, block)
+        }
+    }
+
+    pub fn event_variant_name(&self) -> Ident {
+        util::to_camel_case_ident(&self.source_name)
+    }
+
+    pub fn queue_fn_name(&self) -> Ident {
+        util::ident_append(&str_to_ident("queue_"), &self.source_name)
+    }
+
+    pub fn generate_event_variant(&self) -> TokenStream {
+        let variant = self.event_variant_name();
+        let fields: Vec<TokenStream> = self.args.iter().map(|arg| arg.generate()).collect();
+        let attrs = self.cfgs().into_iter().chain(util::doc_attrs(&self.attrs));
+
+        quote!(#(#attrs)* #variant { #(#fields),* })
+    }
+
+    pub fn set_return(&mut self, ty: Type, aggregate: Aggregate) {
+        self.ret = Some(ty);
+        self.aggregate = aggregate;
+    }
+
+    // Lifetimes used by the signal's types which are not already declared by the system
+    pub fn lifetimes(&self, generics: &Generics) -> Vec<Lifetime> {
+        util::collect_lifetimes(self.args.iter().map(|arg| &arg.ty).chain(self.ret.iter()))
+            .into_iter()
+            .filter(|lifetime| !generics.lifetimes().any(|param| param.lifetime == *lifetime))
+            .collect()
+    }
+
+    pub fn generate(&self, generics: &Generics) -> TokenStream {
+        let mut args: Vec<TokenStream> = self.queue.iter()
+            .map(|queue| util::create_arg(str_to_ident("queue"), quote!(&mut #queue)))
+            .collect();
+        args.extend(self.args.iter().map(|arg| arg.generate_slot()));
+
+        // Only docs and cfgs apply to slots, other attributes are meant for the signal
+        let attrs = self.cfgs().into_iter().chain(util::doc_attrs(&self.attrs));
+        let ret = if self.asynchronous {
+            Some(self.future_ty())
+        } else {
+            self.ret.as_ref().map(|ty| quote!(#ty))
+        };
+
+        let lifetimes = self.lifetimes(generics);
+
+        let item = if let Some(ref block) = self.default {
+            let stmts = &block.stmts;
+            let item = if self.immutable {
+                util::create_default_trait_method(self.dest_name.clone(), &lifetimes, args, ret, quote!(#(#stmts)*))
+            } else {
+                util::create_default_mut_trait_method(self.dest_name.clone(), &lifetimes, args, ret, quote!(#(#stmts)*))
+            };
+
+            // Default slots need not use every argument
+            quote! {
+                #[allow(unused_variables)]
+                #item
+            }
+        } else if self.immutable {
+            util::create_trait_method(self.dest_name.clone(), &lifetimes, args, ret)
+        } else {
+            util::create_mut_trait_method(self.dest_name.clone(), &lifetimes, args, ret)
+        };
+
+        // `ref` arguments are borrowed as given, even when a slice would do
+        let allow = if self.args.iter().any(|arg| arg.pass == ArgPass::Ref) {
+            Some(quote!(#[allow(clippy::ptr_arg)]))
+        } else {
             None
-        )
+        };
+
+        quote!(#(#attrs)* #allow #item)
+    }
+}
+
+impl Aggregate {
+    pub fn from_ident(ident: &Ident) -> Option<Aggregate> {
+        match ident.to_string().as_str() {
+            "collect" => Some(Aggregate::Collect),
+            "first" => Some(Aggregate::First),
+            "any" => Some(Aggregate::Any),
+            "all" => Some(Aggregate::All),
+            "sum" => Some(Aggregate::Sum),
+            "last" => Some(Aggregate::Last),
+            "propagate" => Some(Aggregate::Propagate),
+            _ => None
+        }
+    }
+
+    pub fn generate_ty(&self, ty: &Type) -> TokenStream {
+        match *self {
+            Aggregate::Collect => quote!(Vec<#ty>),
+            Aggregate::Last => quote!(Option<#ty>),
+            Aggregate::Propagate => quote!(bool),
+            Aggregate::First | Aggregate::Any | Aggregate::All | Aggregate::Sum => quote!(#ty)
+        }
+    }
+
+    pub fn generate_init(&self) -> TokenStream {
+        match *self {
+            Aggregate::Collect => quote!(Vec::new()),
+            Aggregate::First | Aggregate::Last => quote!(None),
+            Aggregate::Any | Aggregate::Propagate => quote!(false),
+            Aggregate::All => quote!(true),
+            Aggregate::Sum => quote!(Default::default())
+        }
+    }
+
+    // The signal's return type, and a block aggregating `outputs`, which holds every slot's result
+    // in order, nested in the options or lists they were collected in
+    pub fn generate_outputs(&self, ret: Option<&Type>) -> (Option<TokenStream>, TokenStream) {
+        let [results, result, outputs] = util::local_idents(["results", "result", "outputs"]);
+
+        match ret {
+            Some(ty) => {
+                let init = self.generate_init();
+                let accumulate = self.generate_accumulate(ty);
+
+                (
+                    Some(self.generate_ty(ty)),
+                    quote! {
+                        let mut #results = #init;
+
+                        for #result in #outputs.into_iter().flatten() {
+                            #accumulate
+                        }
+
+                        #results
+                    }
+                )
+            },
+
+            None => (None, quote!(let _ = #outputs;))
+        }
+    }
+
+    pub fn generate_accumulate(&self, ty: &Type) -> TokenStream {
+        let [results, result] = util::local_idents(["results", "result"]);
+
+        match *self {
+            Aggregate::Collect => quote!(#results.push(#result);),
+            Aggregate::First => quote! {
+                if #results.is_none() {
+                    #results = #result;
+                }
+            },
+            Aggregate::Any => quote!(#results = #results || #result;),
+            Aggregate::All => quote!(#results = #results && #result;),
+            Aggregate::Sum => quote!(#results += #result;),
+            Aggregate::Last => quote!(#results = Some(#result);),
+            Aggregate::Propagate => quote! {
+                if let #ty::Stop = #result {
+                    return true
+                }
+            }
+        }
     }
 }
 
 impl HandlerFnArg {
-    pub fn new(name: Ident, ty: Ident, ptr: Option<Mutability>) -> HandlerFnArg {
+    pub fn new(name: Ident, ty: Type, pass: ArgPass) -> HandlerFnArg {
         HandlerFnArg {
-            name: name,
-            ty: ty,
-            ptr: ptr
+            name,
+            ty,
+            pass
+        }
+    }
+
+    pub fn generate(&self) -> TokenStream {
+        let ty = &self.ty;
+        util::create_arg(self.name.clone(), quote!(#ty))
+    }
+
+    pub fn generate_slot(&self) -> TokenStream {
+        util::create_arg(self.name.clone(), self.generate_slot_ty())
+    }
+
+    pub fn generate_slot_ty(&self) -> TokenStream {
+        let ty = &self.ty;
+
+        match self.pass {
+            ArgPass::Ref => quote!(&#ty),
+            ArgPass::Value | ArgPass::Clone => quote!(#ty)
         }
     }
 
-    pub fn generate(&self) -> Arg {
-        util::create_arg(self.name, match self.ptr {
-            Some(Mutability::Immutable) => P(util::ref_ty_from_ident(self.ty)),
-            Some(Mutability::Mutable) => P(util::mut_ref_ty_from_ident(self.ty)),
-            None => P(util::ty_from_ident(self.ty))
-        })
+    // The expression given to each slot for this argument
+    pub fn generate_pass(&self) -> TokenStream {
+        let name = &self.name;
+
+        match self.pass {
+            ArgPass::Value => quote!(#name),
+            ArgPass::Ref => quote!(&#name),
+            ArgPass::Clone => quote!(#name.clone())
+        }
     }
 }
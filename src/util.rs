@@ -18,51 +18,31 @@
 
 #![allow(dead_code)]
 
-use syntax::ast::*;
-use syntax::ptr::P;
-use syntax::parse::token::{str_to_ident, InternedString};
-use syntax::codemap::{respan, Spanned, DUMMY_SP};
-use syntax::abi::Abi;
-
-fn self_arg(mutability: Mutability) -> Arg {
-    Arg {
-        ty: P(Ty {
-            id: DUMMY_NODE_ID,
-            node: TyKind::Rptr(
-                None,
-                MutTy {
-                    ty: P(Ty {
-                        id: DUMMY_NODE_ID,
-                        node: TyKind::ImplicitSelf,
-                        span: DUMMY_SP
-                    }),
-                    mutbl: mutability
-                }
-            ),
-            span: DUMMY_SP
-        }),
-
-        pat: P(Pat {
-            id: DUMMY_NODE_ID,
-            node: PatKind::Ident(
-                BindingMode::ByValue(mutability),
-                Spanned {
-                    span: DUMMY_SP,
-                    node: str_to_ident("self")
-                },
-                None
-            ),
-            span: DUMMY_SP
-        }),
-
-        id: DUMMY_NODE_ID
+use proc_macro2::{Group, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::{Attribute, Generics, Ident, Lifetime, Meta, Type, TypeReference, Visibility};
+use syn::visit::{self, Visit};
+
+fn ret_ty(ret: Option<TokenStream>) -> TokenStream {
+    if let Some(ty) = ret {
+        quote!(-> #ty)
+    } else {
+        TokenStream::new()
+    }
+}
+
+fn generics(lifetimes: &[Lifetime]) -> TokenStream {
+    if lifetimes.is_empty() {
+        TokenStream::new()
+    } else {
+        quote!(<#(#lifetimes),*>)
     }
 }
 
 // https://github.com/rust-lang/rust/blob/213d57983d1640d22bd69e7351731fd1adcbf9b2/src/librustc_lint/bad_style.rs#L148
 fn to_snake_case(mut str: &str) -> String {
     let mut words = vec![];
-    str = str.trim_left_matches(|c: char| {
+    str = str.trim_start_matches(|c: char| {
         if c == '_' {
             words.push(String::new());
             true
@@ -91,949 +71,384 @@ fn to_snake_case(mut str: &str) -> String {
     words.join("_")
 }
 
-pub fn ident_append(a: Ident, b: Ident) -> Ident {
-    let str1 = format!("{}", a.name.as_str());
-    let str2 = format!("{}", b.name.as_str());
-    str_to_ident(&(str1 + &str2))
+fn to_camel_case(str: &str) -> String {
+    str.split('_').filter(|word| !word.is_empty()).map(|word| {
+        let mut chars = word.chars();
+        chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+    }).collect::<Vec<String>>().concat()
 }
 
-pub fn idxs_ident(name: Ident) -> Ident {
-    let mut name = format!("{}", name);
-    ident_append(str_to_ident(&to_snake_case(&mut name)), str_to_ident("_idxs"))
+pub fn str_to_ident(name: &str) -> Ident {
+    Ident::new(name, Span::call_site())
 }
 
-pub fn as_ident(name: Ident) -> Ident {
-    let mut name = format!("{}", name);
-    ident_append(str_to_ident("as_"), str_to_ident(&to_snake_case(&mut name)))
+pub fn ident_append(a: &Ident, b: &Ident) -> Ident {
+    str_to_ident(&format!("{}{}", a, b))
 }
 
-pub fn as_mut_ident(name: Ident) -> Ident {
-    ident_append(as_ident(name), str_to_ident("_mut"))
+pub fn to_camel_case_ident(name: &Ident) -> Ident {
+    str_to_ident(&to_camel_case(&format!("{}", name)))
 }
 
-pub fn ty_from_ident(name: Ident) -> Ty {
-    Ty {
-        id: DUMMY_NODE_ID,
-        node: TyKind::Path(None, Path {
-            span: DUMMY_SP,
-            global: false,
-            segments: vec![PathSegment {
-                identifier: name,
-                parameters: PathParameters::none()
-            }]
-        }),
-        span: DUMMY_SP
-    }
+pub fn idxs_ident(name: &Ident) -> Ident {
+    let name = format!("{}", name);
+    ident_append(&str_to_ident(&to_snake_case(&name)), &str_to_ident("_idxs"))
 }
 
-pub fn ref_ty_from_ident(name: Ident) -> Ty {
-    Ty {
-        id: DUMMY_NODE_ID,
-        node: TyKind::Rptr(
-            None,
-            MutTy {
-                ty: P(ty_from_ident(name)),
-                mutbl: Mutability::Immutable
-            }
-        ),
-        span: DUMMY_SP
-    }
+pub fn as_ident(name: &Ident) -> Ident {
+    let name = format!("{}", name);
+    ident_append(&str_to_ident("as_"), &str_to_ident(&to_snake_case(&name)))
 }
 
-pub fn ref_ty(ty: P<Ty>) -> Ty {
-    Ty {
-        id: DUMMY_NODE_ID,
-        node: TyKind::Rptr(
-            None,
-            MutTy {
-                ty: ty,
-                mutbl: Mutability::Immutable
-            }
-        ),
-        span: DUMMY_SP
-    }
+pub fn as_mut_ident(name: &Ident) -> Ident {
+    ident_append(&as_ident(name), &str_to_ident("_mut"))
 }
 
-pub fn mut_ref_ty_from_ident(name: Ident) -> Ty {
-    Ty {
-        id: DUMMY_NODE_ID,
-        node: TyKind::Rptr(
-            None,
-            MutTy {
-                ty: P(ty_from_ident(name)),
-                mutbl: Mutability::Mutable
-            }
-        ),
-        span: DUMMY_SP
-    }
-}
+pub fn collect_lifetimes<'a, I: Iterator<Item = &'a Type>>(tys: I) -> Vec<Lifetime> {
+    struct Lifetimes(Vec<Lifetime>);
 
-pub fn mut_ref_ty(ty: P<Ty>) -> Ty {
-    Ty {
-        id: DUMMY_NODE_ID,
-        node: TyKind::Rptr(
-            None,
-            MutTy {
-                ty: ty,
-                mutbl: Mutability::Mutable
+    impl<'ast> Visit<'ast> for Lifetimes {
+        fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+            if lifetime.ident != "static" && lifetime.ident != "_" && !self.0.contains(lifetime) {
+                self.0.push(lifetime.clone());
             }
-        ),
-        span: DUMMY_SP
-    }
-}
 
-pub fn param_ty_from_ident(name: Ident, ty: Ty) -> Ty {
-    Ty {
-        id: DUMMY_NODE_ID,
-        span: DUMMY_SP,
-        node: TyKind::Path(None, Path {
-            span: DUMMY_SP,
-            global: false,
-            segments: vec![PathSegment {
-                identifier: name,
-                parameters: PathParameters::AngleBracketed(AngleBracketedParameterData {
-                    lifetimes: Vec::new(),
-                    types: P::from_vec(vec![P(ty)]),
-                    bindings: P::from_vec(Vec::new())
-                })
-            }]
-        })
+            visit::visit_lifetime(self, lifetime);
+        }
     }
-}
 
-pub fn path_param_ty(names: Vec<Ident>, ty: Ty) -> Ty {
-    let mut segments: Vec<PathSegment> = names.iter().map(|name| PathSegment {
-        identifier: *name,
-        parameters: PathParameters::none()
-    }).collect();
-
-    {
-        let mut last = segments.last_mut().unwrap();
-        last.parameters = PathParameters::AngleBracketed(AngleBracketedParameterData {
-            lifetimes: Vec::new(),
-            types: P::from_vec(vec![P(ty)]),
-            bindings: P::from_vec(Vec::new())
-        });
-    }
+    let mut lifetimes = Lifetimes(Vec::new());
 
-    Ty {
-        id: DUMMY_NODE_ID,
-        span: DUMMY_SP,
-        node: TyKind::Path(None, Path {
-            span: DUMMY_SP,
-            global: false,
-            segments: segments
-        })
+    for ty in tys {
+        lifetimes.visit_type(ty);
     }
-}
 
-pub fn box_new(expr: P<Expr>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Call(
-            P(Expr {
-                id: DUMMY_NODE_ID,
-                node: ExprKind::Path(
-                    None,
-                    Path {
-                        span: DUMMY_SP,
-                        global: false,
-                        segments: vec![
-                            PathSegment {
-                                identifier: str_to_ident("Box"),
-                                parameters: PathParameters::none()
-                            },
-                            PathSegment {
-                                identifier: str_to_ident("new"),
-                                parameters: PathParameters::none()
-                            }
-                        ]
-                    }
-                ),
-                span: DUMMY_SP,
-                attrs: None
-            }),
-            vec![expr]
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
+    lifetimes.0
 }
 
-pub fn vec_new() -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Call(
-            P(Expr {
-                id: DUMMY_NODE_ID,
-                node: ExprKind::Path(
-                    None,
-                    Path {
-                        span: DUMMY_SP,
-                        global: false,
-                        segments: vec![
-                            PathSegment {
-                                identifier: str_to_ident("Vec"),
-                                parameters: PathParameters::none()
-                            },
-                            PathSegment {
-                                identifier: str_to_ident("new"),
-                                parameters: PathParameters::none()
-                            }
-                        ]
-                    }
-                ),
-                span: DUMMY_SP,
-                attrs: None
-            }),
-            Vec::new()
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
-}
+// Whether values of a type can outlive the call they were passed to, i.e. it borrows nothing but `'static` data
+pub fn is_owned(ty: &Type) -> bool {
+    struct Owned(bool);
 
-pub fn create_struct_field(name: Ident, ty: P<Ty>) -> StructField {
-    StructField {
-        span: DUMMY_SP,
-        ident: Some(name),
-        vis: Visibility::Inherited,
-        id: DUMMY_NODE_ID,
-        ty: ty,
-        attrs: Vec::new()
-    }
-}
+    impl<'ast> Visit<'ast> for Owned {
+        fn visit_type_reference(&mut self, reference: &'ast TypeReference) {
+            if reference.lifetime.is_none() {
+                self.0 = false;
+            }
+
+            visit::visit_type_reference(self, reference);
+        }
 
-pub fn create_tuple_struct(name: Ident, fields: Vec<P<Ty>>) -> Item {
-    Item {
-        ident: name,
-        attrs: Vec::new(),
-        node: ItemKind::Struct(
-            VariantData::Tuple(
-                fields.into_iter().map(|ty| StructField {
-                    span: DUMMY_SP,
-                    ident: None,
-                    vis: Visibility::Inherited,
-                    id: DUMMY_NODE_ID,
-                    ty: ty,
-                    attrs: Vec::new()
-                }).collect(),
-                DUMMY_NODE_ID
-            ),
-            Default::default()
-        ),
-        id: DUMMY_NODE_ID,
-        span: DUMMY_SP,
-        vis: Visibility::Public
+        fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+            if lifetime.ident != "static" {
+                self.0 = false;
+            }
+        }
     }
+
+    let mut owned = Owned(true);
+    owned.visit_type(ty);
+    owned.0
 }
 
-pub fn create_struct(name: Ident, fields: Vec<StructField>) -> Item {
-    Item {
-        ident: name,
-        attrs: Vec::new(),
-        node: ItemKind::Struct(
-            VariantData::Struct(
-                fields,
-                DUMMY_NODE_ID
-            ),
-            Default::default()
-        ),
-        id: DUMMY_NODE_ID,
-        span: DUMMY_SP,
-        vis: Visibility::Public
+// Whether a type refers to any of the given names, such as a system's type parameters
+pub fn mentions_any(ty: &Type, names: &[Ident]) -> bool {
+    struct Mentions<'a>(&'a [Ident], bool);
+
+    impl<'a, 'ast> Visit<'ast> for Mentions<'a> {
+        fn visit_ident(&mut self, ident: &'ast Ident) {
+            if self.0.contains(ident) {
+                self.1 = true;
+            }
+        }
     }
+
+    let mut mentions = Mentions(names, false);
+    mentions.visit_type(ty);
+    mentions.1
 }
 
-pub fn create_arg(name: Ident, ty: P<Ty>) -> Arg {
-    Arg {
-        ty: ty,
-        pat: P(Pat {
-            id: DUMMY_NODE_ID,
-            node: PatKind::Ident(
-                BindingMode::ByValue(Mutability::Immutable),
-                respan(DUMMY_SP, name),
-                None
-            ),
-            span: DUMMY_SP
-        }),
-        id: DUMMY_NODE_ID
-    }
+// Identifiers for locals of generated code, which are hygienic so they cannot shadow the signal's
+// arguments or be named by default slot bodies
+pub fn local_idents<const N: usize>(names: [&str; N]) -> [Ident; N] {
+    names.map(|name| Ident::new(name, Span::mixed_site()))
 }
 
-pub fn create_mut_trait_method(name: Ident, args: Vec<Arg>, ret: Option<P<Ty>>) -> TraitItem {
-    let mut args = args;
-    args.insert(0, self_arg(Mutability::Mutable));
-
-    TraitItem {
-        id: DUMMY_NODE_ID,
-        ident: name,
-        attrs: Vec::new(),
-        node: TraitItemKind::Method(
-            MethodSig {
-                unsafety: Unsafety::Normal,
-                constness: Constness::NotConst,
-                abi: Abi::Rust,
-                decl: P(FnDecl {
-                    inputs: args,
-                    output: if let Some(ty) = ret {
-                        FunctionRetTy::Ty(ty)
-                    } else {
-                        FunctionRetTy::Default(DUMMY_SP)
-                    },
-                    variadic: false
-                }),
-                generics: Default::default(),
+// Traits in the prelude, which bounds can name from any module
+pub const PRELUDE_TRAITS: &[&str] = &[
+    "AsMut", "AsRef", "Clone", "Copy", "Default", "DoubleEndedIterator", "Drop", "Eq", "ExactSizeIterator", "Extend",
+    "Fn", "FnMut", "FnOnce", "From", "FromIterator", "Into", "IntoIterator", "Iterator", "Ord", "PartialEq", "PartialOrd",
+    "Send", "Sized", "Sync", "ToOwned", "ToString", "TryFrom", "TryInto", "Unpin"
+];
+
+// Replaces paths starting with `crate::` with `$crate::`, so a definition placed in an exported
+// macro still names items in the crate that defined it
+pub fn dollar_crate(tokens: TokenStream) -> TokenStream {
+    let mut output = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ref ident) if ident == "crate" && matches!(tokens.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == ':') => {
+                let mut dollar = Punct::new('$', Spacing::Alone);
+                dollar.set_span(ident.span());
+                output.push(TokenTree::Punct(dollar));
+                output.push(token);
             },
-            None
-        ),
-        span: DUMMY_SP
-    }
-}
 
-pub fn create_trait_method(name: Ident, args: Vec<Arg>, ret: Option<P<Ty>>) -> TraitItem {
-    let mut args = args;
-    args.insert(0, self_arg(Mutability::Immutable));
-
-    TraitItem {
-        id: DUMMY_NODE_ID,
-        ident: name,
-        attrs: Vec::new(),
-        node: TraitItemKind::Method(
-            MethodSig {
-                unsafety: Unsafety::Normal,
-                constness: Constness::NotConst,
-                abi: Abi::Rust,
-                decl: P(FnDecl {
-                    inputs: args,
-                    output: if let Some(ty) = ret {
-                        FunctionRetTy::Ty(ty)
-                    } else {
-                        FunctionRetTy::Default(DUMMY_SP)
-                    },
-                    variadic: false
-                }),
-                generics: Default::default(),
+            TokenTree::Group(ref group) => {
+                let mut replaced = Group::new(group.delimiter(), dollar_crate(group.stream()));
+                replaced.set_span(group.span());
+                output.push(TokenTree::Group(replaced));
             },
-            None
-        ),
-        span: DUMMY_SP
-    }
-}
 
-pub fn create_block_expr(block: P<Block>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Block(block),
-        span: DUMMY_SP,
-        attrs: None
+            token => output.push(token)
+        }
     }
-}
 
-pub fn create_idx_expr(idx: P<Expr>, expr: P<Expr>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Index(expr, idx),
-        span: DUMMY_SP,
-        attrs: None
-    }
+    output.into_iter().collect()
 }
 
-pub fn create_var_expr(name: Ident) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Path(
-            None,
-            Path {
-                span: DUMMY_SP,
-                global: false,
-                segments: vec![
-                    PathSegment {
-                        identifier: name,
-                        parameters: PathParameters::none()
-                    }
-                ]
-            }
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
-}
+// Levenshtein distance between two strings
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
 
-pub fn create_closure_expr(args: Vec<Arg>, block: P<Block>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Closure(
-            CaptureBy::Value,
-            P(FnDecl {
-                inputs: args,
-                output: FunctionRetTy::Default(DUMMY_SP),
-                variadic: false
-            }),
-            block,
-            DUMMY_SP
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
-}
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
 
-pub fn create_tuple_field_expr(expr: P<Expr>, field: usize) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::TupField(
-            expr,
-            respan(DUMMY_SP, field)
-        ),
-        span: DUMMY_SP,
-        attrs: None
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = if ca == cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(prev + 1).min(row[j] + 1);
+        }
     }
-}
 
-pub fn create_num_expr(num: u64) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Lit(P(respan(DUMMY_SP, LitKind::Int(
-            num,
-            LitIntType::Unsuffixed
-        )))),
-        span: DUMMY_SP,
-        attrs: None
-    }
+    row[b.len()]
 }
 
-pub fn create_struct_expr(name: Ident, fields: Vec<Field>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Struct(
-            Path {
-                span: DUMMY_SP,
-                global: false,
-                segments: vec![
-                    PathSegment {
-                        identifier: name,
-                        parameters: PathParameters::none()
-                    }
-                ]
-            },
-            fields,
-            None
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
-}
+// The candidate closest to the given name, if any is close enough to be a likely typo
+pub fn closest_match<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max = std::cmp::max(name.len(), 3) / 3;
 
-pub fn create_deref_expr(expr: P<Expr>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Unary(
-            UnOp::Deref,
-            expr
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
 }
 
-pub fn create_self_field_expr(name: Ident) -> Expr {
-    create_field_expr(name, str_to_ident("self"))
+// The macro each system exports, which is invoked to find the system's definition
+pub fn system_macro_ident(name: &Ident) -> Ident {
+    Ident::new(&format!("__handlers_system_{}", name), name.span())
 }
 
-pub fn create_field_expr(name: Ident, on: Ident) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Field(
-            P(Expr {
-                id: DUMMY_NODE_ID,
-                node: ExprKind::Path(
-                    None,
-                    Path {
-                        span: DUMMY_SP,
-                        global: false,
-                        segments: vec![
-                            PathSegment {
-                                identifier: on,
-                                parameters: PathParameters::none()
-                            }
-                        ]
-                    }
-                ),
-                span: DUMMY_SP,
-                attrs: None
-            }),
-            respan(DUMMY_SP, name)
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
+pub fn cfg_attrs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("cfg")).collect()
 }
 
-pub fn create_if_expr(i: P<Expr>, t: P<Block>, e: Option<P<Expr>>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::If(i, t, e),
-        span: DUMMY_SP,
-        attrs: None
-    }
+pub fn doc_attrs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("doc")).collect()
 }
 
-pub fn create_if_let_expr(p: P<Pat>, i: P<Expr>, t: P<Block>, e: Option<P<Expr>>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::IfLet(p, i, t, e),
-        span: DUMMY_SP,
-        attrs: None
-    }
-}
+// Unwraps attributes of the form `#[name(...)]`, which are forwarded as `#[...]`
+pub fn nested_attrs(attrs: &[Attribute], name: &str) -> Vec<TokenStream> {
+    attrs.iter().filter_map(|attr| match attr.meta {
+        Meta::List(ref list) if list.path.is_ident(name) => {
+            let tokens = &list.tokens;
+            Some(quote!(#[#tokens]))
+        },
 
-pub fn create_loop_expr(block: P<Block>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Loop(block, None),
-        span: DUMMY_SP,
-        attrs: None
-    }
+        _ => None
+    }).collect()
 }
 
-pub fn create_for_expr(name: Ident, range: P<Expr>, block: P<Block>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::ForLoop(
-            P(Pat {
-                id: DUMMY_NODE_ID,
-                node: PatKind::Ident(
-                    BindingMode::ByValue(Mutability::Immutable),
-                    respan(DUMMY_SP, name),
-                    None
-                ),
-                span: DUMMY_SP
-            }),
-            range,
-            block,
-            None
-        ),
-        span: DUMMY_SP,
-        attrs: None
-    }
+pub fn create_struct_field(name: Ident, ty: TokenStream) -> TokenStream {
+    quote!(#name: #ty)
 }
 
-pub fn create_binop_expr(left: P<Expr>, op: BinOpKind, right: P<Expr>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Binary(respan(DUMMY_SP, op), left, right),
-        span: DUMMY_SP,
-        attrs: None
+pub fn create_tuple_struct(vis: &Visibility, name: Ident, fields: Vec<TokenStream>) -> TokenStream {
+    quote! {
+        #vis struct #name(#(#fields),*);
     }
 }
 
-pub fn create_assign_expr(left: P<Expr>, right: P<Expr>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Assign(left, right),
-        span: DUMMY_SP,
-        attrs: None
+pub fn create_struct(vis: &Visibility, name: Ident, generics: &Generics, fields: Vec<TokenStream>) -> TokenStream {
+    let where_clause = &generics.where_clause;
+
+    quote! {
+        #vis struct #name #generics #where_clause {
+            #(#fields),*
+        }
     }
 }
 
-pub fn create_assignop_expr(left: P<Expr>, op: BinOpKind, right: P<Expr>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::AssignOp(respan(DUMMY_SP, op), left, right),
-        span: DUMMY_SP,
-        attrs: None
+pub fn create_enum(vis: &Visibility, name: Ident, variants: Vec<Ident>) -> TokenStream {
+    quote! {
+        #vis enum #name {
+            #(#variants),*
+        }
     }
 }
 
-pub fn impl_method(name: Ident, args: Vec<Arg>, ret: Option<P<Ty>>, block: P<Block>) -> ImplItem {
-    let mut args = args;
-    args.insert(0, self_arg(Mutability::Immutable));
-
-    ImplItem {
-        id: DUMMY_NODE_ID,
-        ident: name,
-        vis: Visibility::Public,
-        defaultness: Defaultness::Final,
-        attrs: Vec::new(),
-        span: DUMMY_SP,
-        node: ImplItemKind::Method(
-            MethodSig {
-                unsafety: Unsafety::Normal,
-                constness: Constness::NotConst,
-                abi: Abi::Rust,
-                decl: P(FnDecl {
-                    inputs: args,
-                    output: if let Some(ty) = ret {
-                        FunctionRetTy::Ty(ty)
-                    } else {
-                        FunctionRetTy::Default(DUMMY_SP)
-                    },
-                    variadic: false
-                }),
-                generics: Default::default(),
-            },
-            block
-        )
+pub fn create_struct_enum(vis: &Visibility, name: Ident, variants: Vec<TokenStream>) -> TokenStream {
+    quote! {
+        #vis enum #name {
+            #(#variants),*
+        }
     }
 }
 
-pub fn impl_method_priv(name: Ident, args: Vec<Arg>, ret: Option<P<Ty>>, block: P<Block>) -> ImplItem {
-    let mut args = args;
-    args.insert(0, self_arg(Mutability::Immutable));
-
-    ImplItem {
-        id: DUMMY_NODE_ID,
-        ident: name,
-        vis: Visibility::Inherited,
-        defaultness: Defaultness::Final,
-        attrs: Vec::new(),
-        span: DUMMY_SP,
-        node: ImplItemKind::Method(
-            MethodSig {
-                unsafety: Unsafety::Normal,
-                constness: Constness::NotConst,
-                abi: Abi::Rust,
-                decl: P(FnDecl {
-                    inputs: args,
-                    output: if let Some(ty) = ret {
-                        FunctionRetTy::Ty(ty)
-                    } else {
-                        FunctionRetTy::Default(DUMMY_SP)
-                    },
-                    variadic: false
-                }),
-                generics: Default::default(),
-            },
-            block
-        )
-    }
+pub fn create_arg(name: Ident, ty: TokenStream) -> TokenStream {
+    quote!(#name: #ty)
 }
 
-pub fn impl_static_method(name: Ident, args: Vec<Arg>, ret: Option<P<Ty>>, block: P<Block>) -> ImplItem {
-    ImplItem {
-        id: DUMMY_NODE_ID,
-        ident: name,
-        vis: Visibility::Public,
-        defaultness: Defaultness::Final,
-        attrs: Vec::new(),
-        span: DUMMY_SP,
-        node: ImplItemKind::Method(
-            MethodSig {
-                unsafety: Unsafety::Normal,
-                constness: Constness::NotConst,
-                abi: Abi::Rust,
-                decl: P(FnDecl {
-                    inputs: args,
-                    output: if let Some(ty) = ret {
-                        FunctionRetTy::Ty(ty)
-                    } else {
-                        FunctionRetTy::Default(DUMMY_SP)
-                    },
-                    variadic: false
-                }),
-                generics: Default::default(),
-            },
-            block
-        )
+pub fn create_mut_trait_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        fn #name #generics(&mut self, #(#args),*) #ret;
     }
 }
 
-pub fn impl_mut_method(name: Ident, args: Vec<Arg>, ret: Option<P<Ty>>, block: P<Block>) -> ImplItem {
-    let mut args = args;
-    args.insert(0, self_arg(Mutability::Mutable));
-
-    ImplItem {
-        id: DUMMY_NODE_ID,
-        ident: name,
-        vis: Visibility::Public,
-        defaultness: Defaultness::Final,
-        attrs: Vec::new(),
-        span: DUMMY_SP,
-        node: ImplItemKind::Method(
-            MethodSig {
-                unsafety: Unsafety::Normal,
-                constness: Constness::NotConst,
-                abi: Abi::Rust,
-                decl: P(FnDecl {
-                    inputs: args,
-                    output: if let Some(ty) = ret {
-                        FunctionRetTy::Ty(ty)
-                    } else {
-                        FunctionRetTy::Default(DUMMY_SP)
-                    },
-                    variadic: false
-                }),
-                generics: Default::default(),
-            },
-            block
-        )
+pub fn create_default_mut_trait_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        fn #name #generics(&mut self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn impl_mut_method_priv(name: Ident, args: Vec<Arg>, ret: Option<P<Ty>>, block: P<Block>) -> ImplItem {
-    let mut args = args;
-    args.insert(0, self_arg(Mutability::Mutable));
-
-    ImplItem {
-        id: DUMMY_NODE_ID,
-        ident: name,
-        vis: Visibility::Inherited,
-        defaultness: Defaultness::Final,
-        attrs: Vec::new(),
-        span: DUMMY_SP,
-        node: ImplItemKind::Method(
-            MethodSig {
-                unsafety: Unsafety::Normal,
-                constness: Constness::NotConst,
-                abi: Abi::Rust,
-                decl: P(FnDecl {
-                    inputs: args,
-                    output: if let Some(ty) = ret {
-                        FunctionRetTy::Ty(ty)
-                    } else {
-                        FunctionRetTy::Default(DUMMY_SP)
-                    },
-                    variadic: false
-                }),
-                generics: Default::default(),
-            },
-            block
-        )
+pub fn create_default_trait_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        fn #name #generics(&self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_cast_expr(expr: P<Expr>, ty: P<Ty>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Cast(
-            expr,
-            ty
-        ),
-        span: DUMMY_SP,
-        attrs: None
+pub fn create_trait_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        fn #name #generics(&self, #(#args),*) #ret;
     }
 }
 
-pub fn create_method_call(name: Ident, on: P<Expr>, args: Vec<P<Expr>>) -> Expr {
-    let mut args = args;
-    args.insert(0, on);
-
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::MethodCall(
-            respan(DUMMY_SP, name),
-            Vec::new(),
-            args
-        ),
-        span: DUMMY_SP,
-        attrs: None
+pub fn impl_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        pub fn #name #generics(&self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_call(expr: P<Expr>, args: Vec<P<Expr>>) -> Expr {
-    Expr {
-        id: DUMMY_NODE_ID,
-        node: ExprKind::Call(
-            expr,
-            args
-        ),
-        span: DUMMY_SP,
-        attrs: None
+pub fn impl_method_priv(name: Ident, args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+
+    quote! {
+        fn #name(&self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_block(stmts: Vec<Stmt>, expr: Option<P<Expr>>) -> Block {
-    Block {
-        stmts: stmts,
-        expr: expr,
-        id: DUMMY_NODE_ID,
-        rules: BlockCheckMode::Default,
-        span: DUMMY_SP
+pub fn impl_async_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        pub async fn #name #generics(&self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_unsafe_block(stmts: Vec<Stmt>, expr: Option<P<Expr>>) -> Block {
-    Block {
-        stmts: stmts,
-        expr: expr,
-        id: DUMMY_NODE_ID,
-        rules: BlockCheckMode::Unsafe(UnsafeSource::CompilerGenerated),
-        span: DUMMY_SP
+pub fn impl_async_mut_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        pub async fn #name #generics(&mut self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_return_block(expr: Option<P<Expr>>) -> Block {
-    create_block(
-        vec![create_stmt(P(Expr {
-            id: DUMMY_NODE_ID,
-            node: ExprKind::Ret(expr),
-            span: DUMMY_SP,
-            attrs: None
-        }))],
-        None
-    )
-}
+pub fn impl_static_method(name: Ident, args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
 
-pub fn create_impl(name: Ident, tr: Option<Ident>, items: Vec<ImplItem>) -> Item {
-    Item {
-        ident: name,
-        attrs: Vec::new(),
-        node: ItemKind::Impl(
-            Unsafety::Normal,
-            ImplPolarity::Positive,
-            Default::default(),
-            tr.map(|name| TraitRef {
-                path: Path {
-                    span: DUMMY_SP,
-                    global: false,
-                    segments: vec![
-                        PathSegment {
-                            identifier: name,
-                            parameters: PathParameters::none()
-                        }
-                    ]
-                },
-                ref_id: DUMMY_NODE_ID
-            }),
-            P(ty_from_ident(name)),
-            items
-        ),
-        id: DUMMY_NODE_ID,
-        span: DUMMY_SP,
-        vis: Visibility::Inherited
+    quote! {
+        pub fn #name(#(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_trait(name: Ident, reqs: &Vec<Ident>, items: &Vec<TraitItem>) -> Item {
-    Item {
-        ident: name,
-        attrs: Vec::new(),
-        node: ItemKind::Trait(
-            Unsafety::Normal,
-            Default::default(),
-            P::from_vec(reqs.iter().map(|req| TyParamBound::TraitTyParamBound(
-                PolyTraitRef {
-                    bound_lifetimes: Vec::new(),
-                    trait_ref: TraitRef {
-                        path: Path {
-                            span: DUMMY_SP,
-                            global: false,
-                            segments: vec![
-                                PathSegment {
-                                    identifier: *req,
-                                    parameters: PathParameters::none()
-                                }
-                            ]
-                        },
-                        ref_id: DUMMY_NODE_ID
-                    },
-                    span: DUMMY_SP
-                },
-                TraitBoundModifier::None
-            )).collect()),
-            items.clone()
-        ),
-        id: DUMMY_NODE_ID,
-        span: DUMMY_SP,
-        vis: Visibility::Public
+pub fn impl_mut_method(name: Ident, lifetimes: &[Lifetime], args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+    let generics = generics(lifetimes);
+
+    quote! {
+        pub fn #name #generics(&mut self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_field(name: Ident, value: P<Expr>) -> Field {
-    Field {
-        ident: respan(DUMMY_SP, name),
-        expr: value,
-        span: DUMMY_SP
+pub fn impl_mut_method_priv(name: Ident, args: Vec<TokenStream>, ret: Option<TokenStream>, block: TokenStream) -> TokenStream {
+    let ret = ret_ty(ret);
+
+    quote! {
+        fn #name(&mut self, #(#args),*) #ret {
+            #block
+        }
     }
 }
 
-pub fn create_let_mut_stmt(name: Ident, expr: Option<P<Expr>>) -> Stmt {
-    respan(DUMMY_SP, StmtKind::Decl(
-        P(respan(DUMMY_SP, DeclKind::Local(
-            P(Local {
-                pat: P(Pat {
-                    id: DUMMY_NODE_ID,
-                    node: PatKind::Ident(
-                        BindingMode::ByValue(Mutability::Mutable),
-                        respan(DUMMY_SP, name),
-                        None
-                    ),
-                    span: DUMMY_SP
-                }),
-                ty: None,
-                init: expr,
-                id: DUMMY_NODE_ID,
-                span: DUMMY_SP,
-                attrs: None
-            })
-        ))),
-        DUMMY_NODE_ID
-    ))
-}
+pub fn create_impl(ty: TokenStream, generics: &Generics, tr: Option<TokenStream>, items: Vec<TokenStream>) -> TokenStream {
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let tr = tr.map(|tr| quote!(#tr for));
 
-pub fn create_let_stmt(name: Ident, expr: Option<P<Expr>>) -> Stmt {
-    respan(DUMMY_SP, StmtKind::Decl(
-        P(respan(DUMMY_SP, DeclKind::Local(
-            P(Local {
-                pat: P(Pat {
-                    id: DUMMY_NODE_ID,
-                    node: PatKind::Ident(
-                        BindingMode::ByValue(Mutability::Immutable),
-                        respan(DUMMY_SP, name),
-                        None
-                    ),
-                    span: DUMMY_SP
-                }),
-                ty: None,
-                init: expr,
-                id: DUMMY_NODE_ID,
-                span: DUMMY_SP,
-                attrs: None
-            })
-        ))),
-        DUMMY_NODE_ID
-    ))
+    quote! {
+        impl #impl_generics #tr #ty #where_clause {
+            #(#items)*
+        }
+    }
 }
 
-pub fn create_stmt(expr: P<Expr>) -> Stmt {
-    respan(DUMMY_SP, StmtKind::Semi(expr, DUMMY_NODE_ID))
-}
+pub fn create_trait(vis: &Visibility, name: Ident, generics: &Generics, reqs: &Vec<Ident>, items: &Vec<TokenStream>) -> TokenStream {
+    let reqs = if reqs.is_empty() {
+        TokenStream::new()
+    } else {
+        quote!(: #(#reqs)+*)
+    };
 
-pub fn create_tuple_struct_pat(name: Ident, bindings: Vec<Ident>) -> Pat {
-    Pat {
-        id: DUMMY_NODE_ID,
-        node: PatKind::TupleStruct(
-            Path {
-                span: DUMMY_SP,
-                global: false,
-                segments: vec![PathSegment {
-                    identifier: name,
-                    parameters: PathParameters::none()
-                }]
-            },
-            bindings.into_iter().map(|ident| P(Pat {
-                id: DUMMY_NODE_ID,
-                node: PatKind::Ident(
-                    BindingMode::ByValue(Mutability::Immutable),
-                    respan(DUMMY_SP, ident),
-                    None
-                ),
-                span: DUMMY_SP
-            })).collect(),
-            None
-        ),
-        span: DUMMY_SP
+    let where_clause = &generics.where_clause;
+
+    quote! {
+        #vis trait #name #generics #reqs #where_clause {
+            #(#items)*
+        }
     }
 }
 
-pub fn create_derive(items: Vec<InternedString>) -> Attribute {
-    respan(DUMMY_SP, Attribute_ {
-        id: AttrId(0),
-        style: AttrStyle::Outer,
-        value: P(respan(DUMMY_SP, MetaItemKind::List(
-            InternedString::new("derive"),
-            items.into_iter().map(|item| P(respan(DUMMY_SP, MetaItemKind::Word(item)))).collect()
-        ))),
-        is_sugared_doc: false
-    })
+pub fn create_derive(items: Vec<&str>) -> TokenStream {
+    let items = items.into_iter().map(str_to_ident);
+
+    quote! {
+        #[derive(#(#items),*)]
+    }
 }
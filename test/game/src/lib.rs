@@ -0,0 +1,36 @@
+//! A crate defining systems that objects in other crates are added to.
+
+pub mod events {
+    use handlers::handlers_define_system;
+
+    handlers_define_system! {
+        pub System {
+            UpdateHandler {
+                update(dt: u32) -> u32 => on_update [sum]
+            }
+
+            NameHandler {
+                name(&self) -> &'static str => on_name
+            }
+        }
+    }
+}
+
+pub mod gfx {
+    pub trait Renderer {
+        fn name(&self) -> &'static str;
+    }
+}
+
+pub mod scene {
+    use handlers::handlers_define_system;
+
+    // Bounds are named by their full path, so objects can be implemented outside this module
+    handlers_define_system! {
+        pub Scene<R: crate::gfx::Renderer> {
+            DrawHandler {
+                draw(renderer: &R) -> String => on_draw
+            }
+        }
+    }
+}
@@ -1,21 +1,35 @@
-#![feature(plugin, box_syntax)]
-#![plugin(handlers)]
+use handlers::{handlers_define_system, handlers_impl_object};
 
 pub trait Renderable {
     fn render(&self);
     fn update(&mut self, x: i64);
 }
 
-handlers_define_system! System {
-    * : Renderable;
+handlers_define_system! {
+    System {
+        * : Renderable;
 
-    MouseHandler {
-        click(x: u64, y: u64) => on_click;
-        hover() => on_hover
-    }
+        MouseHandler {
+            click(x: u64, y: u64) => on_click;
+            hover() => on_hover
+        }
+
+        InputHandler {
+            input(input: char) => on_input;
+            key(code: i64) => on_key [propagate]
+        }
+
+        DataHandler {
+            data(bytes: &[u8], pos: (f32, f32), delay: Option<std::time::Duration>) => on_data;
+            label(text: &'a str, tags: &[&'a str; 2]) => on_label
+        }
 
-    InputHandler {
-        input(input: char) => on_input
+        QueryHandler {
+            total() -> i64 => on_total [sum];
+            values() -> i64 => on_value;
+            above(n: i64) -> bool => on_above [any];
+            find(n: i64) -> Option<i64> => on_find [first]
+        }
     }
 }
 
@@ -26,7 +40,45 @@ pub struct Test {
 impl InputHandler for Test {
     fn on_input(&mut self, input: char) {
         println!("{}: {}", self.n, input);
-        self.n = self.n + 1;
+        self.n += 1;
+    }
+
+    fn on_key(&mut self, code: i64) -> SystemPropagation {
+        println!("{}: key {}", self.n, code);
+
+        if code == self.n {
+            SystemPropagation::Stop
+        } else {
+            SystemPropagation::Continue
+        }
+    }
+}
+
+impl DataHandler for Test {
+    fn on_data(&mut self, bytes: &[u8], pos: (f32, f32), delay: Option<std::time::Duration>) {
+        println!("{}: {:?} at {:?} after {:?}", self.n, bytes, pos, delay);
+    }
+
+    fn on_label<'a>(&mut self, text: &'a str, tags: &[&'a str; 2]) {
+        println!("{}: {} {:?}", self.n, text, tags);
+    }
+}
+
+impl QueryHandler for Test {
+    fn on_total(&mut self) -> i64 {
+        self.n
+    }
+
+    fn on_value(&mut self) -> i64 {
+        self.n
+    }
+
+    fn on_above(&mut self, n: i64) -> bool {
+        self.n > n
+    }
+
+    fn on_find(&mut self, n: i64) -> Option<i64> {
+        if self.n == n { Some(self.n) } else { None }
     }
 }
 
@@ -40,18 +92,22 @@ impl Renderable for Test {
     }
 }
 
-handlers_impl_object! System { 
-    Test: InputHandler 
+handlers_impl_object! {
+    System {
+        Test: InputHandler, DataHandler, QueryHandler
+    }
 }
 
 fn main() {
     let mut system = System::new();
-    let idx = system.add(box Test{n: 15});
+    let idx = system.add(Box::new(Test{n: 15}));
     for obj in system.iter() { obj.render(); }
     system.input('H');
     system.input('e');
-    system.add(box Test{n: 20});
+    system.add(Box::new(Test{n: 20}));
     for obj in system.iter() { obj.render(); }
+    println!("Consumed: {}", system.key(17));
+    println!("Consumed: {}", system.key(0));
     system.input('l');
     system.hover();
     system.input('l');
@@ -60,7 +116,19 @@ fn main() {
     for obj in system.iter() { obj.render(); }
     system.input('o');
     system.input('!');
-    system.add(box Test{n: 25});
+    system.queue_input('.');
+    system.process_events();
+    system.dispatch(SystemEvent::Key { code: 0 });
+    system.data(&[1, 2, 3], (0.5, 1.5), Some(std::time::Duration::from_millis(10)));
+    system.label("tagged", &["a", "b"]);
+    println!("Values: {:?}, total: {}", system.values(), system.total());
+    println!("Above 20: {}, find 24: {:?}", system.above(20), system.find(24));
+    let new_idx = system.add(Box::new(Test{n: 25}));
+    system.input_to(new_idx, '#');
+    println!("Key to {}: {:?}", 25, system.key_to(new_idx, 25));
+    assert!(!system.click_to(new_idx, 0, 0));
+    system.add_with_priority(Box::new(Test{n: 100}), 1);
+    system.input('?');
     for obj in system.iter() { obj.render(); }
     for obj in system.iter_mut() { obj.update(-10); obj.render(); }
 }
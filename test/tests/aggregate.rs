@@ -0,0 +1,82 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        QueryHandler {
+            values() -> i64 => on_value;
+            find(n: i64) -> Option<i64> => on_find [first];
+            above(n: i64) -> bool => on_above [any];
+            positive() -> bool => on_positive [all];
+            total() -> i64 => on_total [sum];
+            latest() -> i64 => on_latest [last]
+        }
+    }
+}
+
+struct Value(i64);
+
+impl QueryHandler for Value {
+    fn on_value(&mut self) -> i64 {
+        self.0
+    }
+
+    fn on_find(&mut self, n: i64) -> Option<i64> {
+        if self.0 >= n { Some(self.0) } else { None }
+    }
+
+    fn on_above(&mut self, n: i64) -> bool {
+        self.0 > n
+    }
+
+    fn on_positive(&mut self) -> bool {
+        self.0 > 0
+    }
+
+    fn on_total(&mut self) -> i64 {
+        self.0
+    }
+
+    fn on_latest(&mut self) -> i64 {
+        self.0
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Value: QueryHandler
+    }
+}
+
+#[test]
+fn aggregations() {
+    let mut system = System::new();
+    system.add(Box::new(Value(3)));
+    system.add(Box::new(Value(-1)));
+    system.add(Box::new(Value(7)));
+
+    assert_eq!(system.values(), vec![3, -1, 7]);
+    assert_eq!(system.find(2), Some(3));
+    assert_eq!(system.find(5), Some(7));
+    assert_eq!(system.find(8), None);
+    assert!(system.above(6));
+    assert!(!system.above(7));
+    assert!(!system.positive());
+    assert_eq!(system.total(), 9);
+    assert_eq!(system.latest(), Some(7));
+}
+
+#[test]
+fn aggregations_without_handlers() {
+    let mut system = System::new();
+
+    assert_eq!(system.values(), Vec::<i64>::new());
+    assert_eq!(system.find(0), None);
+    assert!(!system.above(0));
+    assert!(system.positive());
+    assert_eq!(system.total(), 0);
+    assert_eq!(system.latest(), None);
+
+    system.add(Box::new(Value(1)));
+    system.add(Box::new(Value(2)));
+    assert!(system.positive());
+}
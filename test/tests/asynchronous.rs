@@ -0,0 +1,113 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use handlers::{handlers_define_system, handlers_impl_object};
+
+// Futures are only required to be Send, whatever the other object bounds
+handlers_define_system! {
+    System [Send + Sync] {
+        NetworkHandler {
+            async fetch(id: u32) -> String => on_fetch;
+            async total(&self) -> u32 => on_total [sum];
+            async close(&self, code: u32) => on_close [propagate];
+            #[default]
+            async flush() => on_flush;
+            ping() -> u32 => on_ping [sum]
+        }
+    }
+}
+
+// Returns pending a number of times before completing
+struct Delay(u32);
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 == 0 {
+            Poll::Ready(())
+        } else {
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+fn block_on<F: Future>(future: F) -> (F::Output, u32) {
+    let mut future = Box::pin(future);
+    let mut context = Context::from_waker(Waker::noop());
+    let mut polls = 1;
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return (output, polls),
+            Poll::Pending => polls += 1
+        }
+    }
+}
+
+struct Client {
+    name: &'static str,
+    delay: u32,
+    fetched: u32
+}
+
+impl NetworkHandler for Client {
+    fn on_fetch(&mut self, id: u32) -> Pin<Box<dyn Future<Output = String> + Send + '_>> {
+        Box::pin(async move {
+            Delay(self.delay).await;
+            self.fetched += 1;
+            format!("{} {}", self.name, id)
+        })
+    }
+
+    fn on_total(&self) -> Pin<Box<dyn Future<Output = u32> + Send + '_>> {
+        Box::pin(async move { self.fetched })
+    }
+
+    fn on_close(&self, code: u32) -> Pin<Box<dyn Future<Output = SystemPropagation> + Send + '_>> {
+        Box::pin(async move {
+            if code == self.delay { SystemPropagation::Stop } else { SystemPropagation::Continue }
+        })
+    }
+
+    fn on_ping(&mut self) -> u32 {
+        1
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Client
+    }
+}
+
+#[test]
+fn async_signals() {
+    let mut system = System::new();
+    let a = system.add(Box::new(Client { name: "a", delay: 3, fetched: 0 }));
+    system.add(Box::new(Client { name: "b", delay: 3, fetched: 0 }));
+
+    // Sequential slots wait for each other, concurrent ones are polled together
+    let (results, polls) = block_on(assert_send(system.fetch(1)));
+    assert_eq!((results, polls), (vec![String::from("a 1"), String::from("b 1")], 7));
+
+    let (results, polls) = block_on(assert_send(system.fetch_concurrent(2)));
+    assert_eq!((results, polls), (vec![String::from("a 2"), String::from("b 2")], 4));
+
+    assert_eq!(block_on(system.total()).0, 4);
+    assert_eq!(block_on(system.total_concurrent()).0, 4);
+    assert_eq!(block_on(assert_send(system.fetch_to(a, 3))).0, Some(String::from("a 3")));
+    assert!(block_on(system.close(3)).0);
+    block_on(system.flush());
+
+    // Signals which are not async are unaffected
+    assert_eq!(system.ping(), 2);
+    assert_eq!(SystemShared::new(system).ping(), 2);
+}
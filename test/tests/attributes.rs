@@ -0,0 +1,62 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    /// A documented system.
+    #[event(derive(Debug, Clone, PartialEq))]
+    pub System {
+        /// Handles ticks.
+        pub TickHandler {
+            /// Advances time.
+            tick(dt: u32) -> u32 => on_tick [sum];
+
+            /// Replaced by `tick`.
+            #[deprecated]
+            step() => on_step
+        }
+
+        /// Never generated.
+        #[cfg(any())]
+        pub MissingHandler {
+            /// Refers to a type that does not exist.
+            missing(value: DoesNotExist) => on_missing
+        }
+    }
+}
+
+struct Clock {
+    time: u32
+}
+
+impl TickHandler for Clock {
+    fn on_tick(&mut self, dt: u32) -> u32 {
+        self.time += dt;
+        self.time
+    }
+
+    fn on_step(&mut self) {
+        self.time += 1;
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Clock: TickHandler
+    }
+}
+
+#[test]
+fn forwarded_attributes() {
+    let mut system = System::new();
+    system.add(Box::new(Clock { time: 0 }));
+
+    #[allow(deprecated)]
+    system.step();
+    assert_eq!(system.tick(2), 3);
+
+    let event = SystemEvent::Tick { dt: 4 };
+    assert_eq!(format!("{:?}", event.clone()), "Tick { dt: 4 }");
+    assert_eq!(event, SystemEvent::Tick { dt: 4 });
+
+    system.dispatch(event);
+    assert_eq!(system.tick(0), 7);
+}
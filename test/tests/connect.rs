@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        InputHandler {
+            key(code: u32) -> u32 => on_key;
+            ref_text(&self, ref text: String) -> usize => on_text [sum];
+            queue repeat(times: u8) => on_repeat;
+            consume(code: u32) => on_consume [propagate]
+        }
+    }
+}
+
+struct Doubler;
+
+impl InputHandler for Doubler {
+    fn on_key(&mut self, code: u32) -> u32 {
+        code * 2
+    }
+
+    fn on_text(&self, text: &String) -> usize {
+        text.len()
+    }
+
+    fn on_repeat(&mut self, _: &mut SystemQueue, _: u8) {}
+
+    fn on_consume(&mut self, _: u32) -> SystemPropagation {
+        SystemPropagation::Continue
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Doubler: InputHandler
+    }
+}
+
+#[test]
+fn closures_after_objects() {
+    let mut system = System::new();
+    system.add(Box::new(Doubler));
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = seen.clone();
+    let token = system.connect_key(move |code| {
+        log.borrow_mut().push(code);
+        code + 1
+    });
+
+    assert_eq!(system.key(4), vec![8, 5]);
+    system.connect_ref_text(|text| text.len() * 10);
+    assert_eq!(system.ref_text(String::from("ab")), 22);
+
+    // Disconnected closures are no longer called, and can only be disconnected once
+    assert!(system.disconnect(token));
+    assert!(!system.disconnect(token));
+    assert_eq!(system.key(1), vec![2]);
+    assert_eq!(*seen.borrow(), vec![4]);
+}
+
+#[test]
+fn closures_queue_and_propagate() {
+    let mut system = System::new();
+    system.add(Box::new(Doubler));
+
+    let count = Rc::new(RefCell::new(0));
+    let counter = count.clone();
+    system.connect_repeat(move |queue, times| {
+        *counter.borrow_mut() += 1;
+
+        if times > 1 {
+            queue.queue_repeat(times - 1);
+        }
+    });
+
+    system.repeat(3);
+    system.process_events();
+    assert_eq!(*count.borrow(), 3);
+
+    assert!(!system.consume(0));
+    system.connect_consume(|code| if code > 0 { SystemPropagation::Stop } else { SystemPropagation::Continue });
+    assert!(!system.consume(0));
+    assert!(system.consume(1));
+}
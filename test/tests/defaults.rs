@@ -0,0 +1,74 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        MouseHandler {
+            click(x: i32, y: i32) -> i32 => on_click { x + y };
+            hover() => on_hover {};
+            queue press(button: u8) => on_press {
+                if button > 0 {
+                    queue.queue_press(button - 1);
+                }
+            }
+        }
+
+        QueryHandler {
+            #[default]
+            count() -> u32 => on_count [sum];
+            #[default]
+            claim(id: u32) => on_claim [propagate];
+            #[default]
+            reset() => on_reset
+        }
+    }
+}
+
+struct Plain;
+
+impl MouseHandler for Plain {}
+impl QueryHandler for Plain {}
+
+struct Counter {
+    presses: u32
+}
+
+impl MouseHandler for Counter {
+    fn on_press(&mut self, _: &mut SystemQueue, _: u8) {
+        self.presses += 1;
+    }
+}
+
+impl QueryHandler for Counter {
+    fn on_count(&mut self) -> u32 {
+        self.presses
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Plain: MouseHandler, QueryHandler
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Counter: MouseHandler, QueryHandler
+    }
+}
+
+#[test]
+fn default_slots() {
+    let mut system = System::new();
+    system.add(Box::new(Plain));
+    system.add(Box::new(Counter { presses: 0 }));
+
+    assert_eq!(system.click(1, 2), vec![3, 3]);
+    system.hover();
+    system.reset();
+    assert!(!system.claim(0));
+
+    // Plain re-queues presses until the button reaches zero
+    system.press(3);
+    system.process_events();
+    assert_eq!(system.count(), 4);
+}
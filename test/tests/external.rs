@@ -0,0 +1,67 @@
+use game::events::{NameHandler, System, UpdateHandler};
+use game::gfx::Renderer;
+use game::scene::{DrawHandler, Scene};
+use handlers::handlers_impl_object;
+
+struct Player {
+    time: u32
+}
+
+impl UpdateHandler for Player {
+    fn on_update(&mut self, dt: u32) -> u32 {
+        self.time += dt;
+        self.time
+    }
+}
+
+impl NameHandler for Player {
+    fn on_name(&self) -> &'static str {
+        "player"
+    }
+}
+
+handlers_impl_object! {
+    game::events::System {
+        Player: UpdateHandler
+    }
+}
+
+struct Sprite;
+
+impl<R: Renderer> DrawHandler<R> for Sprite {
+    fn on_draw(&mut self, renderer: &R) -> String {
+        format!("sprite on {}", renderer.name())
+    }
+}
+
+handlers_impl_object! {
+    game::scene::Scene {
+        Sprite: DrawHandler
+    }
+}
+
+struct Canvas;
+
+impl Renderer for Canvas {
+    fn name(&self) -> &'static str {
+        "canvas"
+    }
+}
+
+#[test]
+fn object_for_external_system() {
+    let mut system = System::new();
+    system.add(Box::new(Player { time: 0 }));
+
+    assert_eq!(system.update(2), 2);
+    assert_eq!(system.update(3), 5);
+    assert_eq!(system.name(), vec!["player"]);
+}
+
+#[test]
+fn object_for_external_generic_system() {
+    let mut scene = Scene::new();
+    scene.add(Box::new(Sprite));
+
+    assert_eq!(scene.draw(&Canvas), vec!["sprite on canvas"]);
+}
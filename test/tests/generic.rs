@@ -0,0 +1,90 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+pub trait Renderer {
+    fn draw(&mut self, what: &str);
+}
+
+#[derive(Default)]
+pub struct Canvas {
+    drawn: Vec<String>
+}
+
+impl Renderer for Canvas {
+    fn draw(&mut self, what: &str) {
+        self.drawn.push(what.to_owned());
+    }
+}
+
+handlers_define_system! {
+    System<R: Renderer, 'a> where R: Default {
+        DrawHandler {
+            draw(renderer: &mut R) => on_draw;
+            label(text: &'a str) -> &'a str => on_label [last];
+            tick(dt: u32) => on_tick
+        }
+    }
+}
+
+struct Sprite {
+    name: &'static str,
+    ticks: u32
+}
+
+impl<'a, R: Renderer + Default> DrawHandler<'a, R> for Sprite {
+    fn on_draw(&mut self, renderer: &mut R) {
+        renderer.draw(self.name);
+    }
+
+    fn on_label(&mut self, text: &'a str) -> &'a str {
+        text
+    }
+
+    fn on_tick(&mut self, dt: u32) {
+        self.ticks += dt;
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Sprite: DrawHandler
+    }
+}
+
+struct Background;
+
+impl<'a> DrawHandler<'a, Canvas> for Background {
+    fn on_draw(&mut self, renderer: &mut Canvas) {
+        renderer.draw("background");
+    }
+
+    fn on_label(&mut self, _: &'a str) -> &'a str {
+        "background"
+    }
+
+    fn on_tick(&mut self, _: u32) {}
+}
+
+handlers_impl_object! {
+    System<'a, Canvas> {
+        Background: DrawHandler
+    }
+}
+
+#[test]
+fn generic_system() {
+    let text = String::from("sprite");
+    let mut system: System<'_, Canvas> = System::new();
+    let mut canvas = Canvas::default();
+
+    system.add_with_priority(Box::new(Background), 1);
+    let sprite = system.add(Box::new(Sprite { name: "sprite", ticks: 0 }));
+
+    system.draw(&mut canvas);
+    assert_eq!(canvas.drawn, vec!["background", "sprite"]);
+    assert_eq!(system.label(&text), Some("sprite"));
+
+    system.queue_tick(2);
+    system.dispatch(SystemEvent::Tick { dt: 3 });
+    system.process_events();
+    assert!(system.remove(sprite).is_some());
+}
@@ -0,0 +1,61 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        RenderHandler {
+            render(&self, frame: u64) -> String => on_render;
+            visible(&self) -> bool => on_visible [any];
+            hide() => on_hide;
+            describe(&self) => on_describe {}
+        }
+    }
+}
+
+struct Sprite {
+    name: &'static str,
+    hidden: bool
+}
+
+impl RenderHandler for Sprite {
+    fn on_render(&self, frame: u64) -> String {
+        format!("{} {}", self.name, frame)
+    }
+
+    fn on_visible(&self) -> bool {
+        !self.hidden
+    }
+
+    fn on_hide(&mut self) {
+        self.hidden = true;
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Sprite: RenderHandler
+    }
+}
+
+fn render_all(system: &System, frame: u64) -> Vec<String> {
+    system.render(frame)
+}
+
+#[test]
+fn immutable_signals() {
+    let mut system = System::new();
+    let a = system.add(Box::new(Sprite { name: "a", hidden: false }));
+    let b = system.add(Box::new(Sprite { name: "b", hidden: false }));
+
+    assert_eq!(render_all(&system, 1), vec!["a 1", "b 1"]);
+    assert_eq!(system.render_to(b, 2), Some(String::from("b 2")));
+    system.describe();
+
+    // Removed objects are skipped without needing mutable access
+    system.remove(a);
+    assert_eq!(render_all(&system, 3), vec!["b 3"]);
+    assert!(system.visible());
+
+    system.hide();
+    assert!(!system.visible());
+    assert_eq!(system.render_to(a, 4), None);
+}
@@ -0,0 +1,81 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System<'a, T: Clone> {
+        InputHandler {
+            input(c: char) -> char => on_input
+        }
+
+        NameHandler {
+            name(&self, prefix: &'a str) -> String => on_name
+        }
+
+        ValueHandler {
+            value(value: &T) -> T => on_value
+        }
+    }
+}
+
+struct Named;
+
+impl<'a, T: Clone> NameHandler<'a, T> for Named {
+    fn on_name(&self, prefix: &'a str) -> String {
+        format!("{}named", prefix)
+    }
+}
+
+// Only implemented for one instantiation, so the handlers it does not list are inferred
+handlers_impl_object! {
+    System<'a, u32> {
+        Named: NameHandler
+    }
+}
+
+struct Echo;
+
+impl<'a, T: Clone> InputHandler<'a, T> for Echo {
+    fn on_input(&mut self, c: char) -> char {
+        c
+    }
+}
+
+impl<'a, T: Clone> NameHandler<'a, T> for Echo {
+    fn on_name(&self, prefix: &'a str) -> String {
+        format!("{}echo", prefix)
+    }
+}
+
+// NameHandler is missing from the list, but is still found
+handlers_impl_object! {
+    System<'a, u32> {
+        Echo: InputHandler
+    }
+}
+
+struct Doubler;
+
+impl<'a> ValueHandler<'a, u32> for Doubler {
+    fn on_value(&mut self, value: &u32) -> u32 {
+        value * 2
+    }
+}
+
+handlers_impl_object! {
+    System<'a, u32> {
+        Doubler
+    }
+}
+
+#[test]
+fn inferred_handlers() {
+    let mut system: System<'_, u32> = System::new();
+    system.add(Box::new(Named));
+    let echo = system.add(Box::new(Echo));
+    system.add(Box::new(Doubler));
+
+    assert_eq!(system.input('x'), vec!['x']);
+    assert_eq!(system.name("a "), vec!["a named", "a echo"]);
+    assert_eq!(system.name_to(echo, "b "), Some(String::from("b echo")));
+    assert_eq!(system.value(&4), vec![8]);
+    assert_eq!(system.value_to(echo, &4), None);
+}
@@ -0,0 +1,210 @@
+use handlers::handlers_impl_object;
+
+mod ui {
+    use handlers::{handlers_define_system, handlers_impl_object};
+
+    handlers_define_system! {
+        pub Events {
+            ClickHandler {
+                click() -> &'static str => on_click
+            }
+        }
+    }
+
+    pub struct Button;
+
+    impl ClickHandler for Button {
+        fn on_click(&mut self) -> &'static str {
+            "button"
+        }
+    }
+
+    handlers_impl_object! {
+        Events {
+            Button: ClickHandler
+        }
+    }
+}
+
+mod audio {
+    use handlers::handlers_define_system;
+
+    handlers_define_system! {
+        pub Events {
+            SoundHandler {
+                play(volume: u8) -> u8 => on_play
+            }
+        }
+    }
+
+    pub mod sources {
+        use handlers::handlers_impl_object;
+
+        pub struct Speaker;
+
+        impl super::SoundHandler for Speaker {
+            fn on_play(&mut self, volume: u8) -> u8 {
+                volume
+            }
+        }
+
+        handlers_impl_object! {
+            super::Events {
+                Speaker
+            }
+        }
+    }
+}
+
+// Identical definitions are still kept apart
+mod first {
+    handlers::handlers_define_system! {
+        pub Events {
+            TickHandler {
+                tick() => on_tick
+            }
+        }
+    }
+}
+
+mod second {
+    handlers::handlers_define_system! {
+        pub Events {
+            TickHandler {
+                tick() => on_tick
+            }
+        }
+    }
+}
+
+// As are systems generated by the same macro
+macro_rules! timer_system {
+    () => {
+        handlers::handlers_define_system! {
+            pub Timer {
+                AlarmHandler {
+                    alarm() -> u8 => on_alarm
+                }
+            }
+        }
+    };
+}
+
+mod kitchen {
+    timer_system!();
+}
+
+mod garden {
+    timer_system!();
+}
+
+struct Oven;
+
+impl kitchen::AlarmHandler for Oven {
+    fn on_alarm(&mut self) -> u8 {
+        1
+    }
+}
+
+handlers_impl_object! {
+    kitchen::Timer {
+        Oven
+    }
+}
+
+struct Sprinkler;
+
+impl garden::AlarmHandler for Sprinkler {
+    fn on_alarm(&mut self) -> u8 {
+        2
+    }
+}
+
+handlers_impl_object! {
+    garden::Timer {
+        Sprinkler
+    }
+}
+
+mod gfx {
+    pub trait Renderer {
+        fn name(&self) -> &'static str;
+    }
+
+    pub mod scene {
+        handlers::handlers_define_system! {
+            pub Scene<R: crate::gfx::Renderer> where R: Clone {
+                DrawHandler {
+                    draw(renderer: &R) -> String => on_draw
+                }
+            }
+        }
+    }
+}
+
+struct Sprite;
+
+impl<R: gfx::Renderer + Clone> gfx::scene::DrawHandler<R> for Sprite {
+    fn on_draw(&mut self, renderer: &R) -> String {
+        format!("sprite on {}", renderer.name())
+    }
+}
+
+handlers_impl_object! {
+    gfx::scene::Scene {
+        Sprite: DrawHandler
+    }
+}
+
+#[derive(Clone)]
+struct Canvas;
+
+impl gfx::Renderer for Canvas {
+    fn name(&self) -> &'static str {
+        "canvas"
+    }
+}
+
+struct Link;
+
+impl ui::ClickHandler for Link {
+    fn on_click(&mut self) -> &'static str {
+        "link"
+    }
+}
+
+handlers_impl_object! {
+    crate::ui::Events {
+        Link: ClickHandler
+    }
+}
+
+#[test]
+fn same_name_in_different_modules() {
+    let mut ui = ui::Events::new();
+    ui.add(Box::new(ui::Button));
+    ui.add(Box::new(Link));
+    assert_eq!(ui.click(), vec!["button", "link"]);
+
+    let mut audio = audio::Events::new();
+    audio.add(Box::new(audio::sources::Speaker));
+    assert_eq!(audio.play(3), vec![3]);
+
+    first::Events::new().tick();
+    second::Events::new().tick();
+
+    let mut kitchen = kitchen::Timer::new();
+    kitchen.add(Box::new(Oven));
+    assert_eq!(kitchen.alarm(), vec![1]);
+
+    let mut garden = garden::Timer::new();
+    garden.add(Box::new(Sprinkler));
+    assert_eq!(garden.alarm(), vec![2]);
+}
+
+#[test]
+fn generic_system_in_other_module() {
+    let mut scene = gfx::scene::Scene::new();
+    scene.add(Box::new(Sprite));
+    assert_eq!(scene.draw(&Canvas), vec!["sprite on canvas"]);
+}
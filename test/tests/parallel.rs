@@ -0,0 +1,79 @@
+use std::thread;
+
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        PhysicsHandler {
+            par step(dt: u64, ref gravity: Vec<u64>) -> u64 => on_step [sum];
+            par thread(&self) -> thread::ThreadId => on_thread;
+            par position(&self, clone label: String) -> String => on_position;
+            par first_above(&self, n: u64) -> Option<u64> => on_first_above [first]
+        }
+    }
+}
+
+struct Body {
+    y: u64
+}
+
+impl PhysicsHandler for Body {
+    fn on_step(&mut self, dt: u64, gravity: &Vec<u64>) -> u64 {
+        self.y += dt * gravity.iter().sum::<u64>();
+        self.y
+    }
+
+    fn on_thread(&self) -> thread::ThreadId {
+        thread::current().id()
+    }
+
+    fn on_position(&self, label: String) -> String {
+        format!("{} {}", label, self.y)
+    }
+
+    fn on_first_above(&self, n: u64) -> Option<u64> {
+        if self.y > n { Some(self.y) } else { None }
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Body
+    }
+}
+
+#[test]
+fn parallel_signals() {
+    let mut system = System::new();
+    let mut idxs = Vec::new();
+
+    for y in 0..100 {
+        idxs.push(system.add_with_priority(Box::new(Body { y }), -(y as i32)));
+    }
+
+    system.remove(idxs[0]);
+    assert_eq!(system.step(1, vec![1, 1]), (1..100).map(|y| y + 2).sum::<u64>());
+
+    // With rayon every slot is called on its pool, otherwise the first chunk is called on this
+    // thread and the rest on spawned threads
+    let threads = system.thread();
+
+    if cfg!(feature = "rayon") {
+        assert!(threads.iter().all(|&id| id != thread::current().id()));
+    } else {
+        assert_eq!(threads[0], thread::current().id());
+
+        if thread::available_parallelism().map_or(1, |threads| threads.get()) > 1 {
+            assert!(threads.iter().any(|&id| id != thread::current().id()));
+        }
+    }
+
+    let positions = system.position(String::from("y"));
+    assert_eq!(positions.len(), 99);
+    assert_eq!(positions[0], "y 3");
+    assert_eq!(positions[98], "y 101");
+
+    // Results are aggregated in priority order
+    assert_eq!(system.first_above(50), Some(51));
+    assert_eq!(system.step_to(idxs[1], 1, vec![0]), Some(3));
+}
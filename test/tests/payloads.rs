@@ -0,0 +1,68 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        ChatHandler {
+            message(ref text: String, clone tags: Vec<String>) => on_message;
+            length(&self, ref text: String) -> usize => on_length [sum];
+            rename(clone name: String, id: u32) => on_rename;
+            state(&self) -> (Vec<String>, String) => on_state
+        }
+    }
+}
+
+#[derive(Default)]
+struct Log {
+    lines: Vec<String>,
+    name: String
+}
+
+impl ChatHandler for Log {
+    fn on_message(&mut self, text: &String, tags: Vec<String>) {
+        self.lines.push(format!("{} {:?}", text, tags));
+    }
+
+    fn on_length(&self, text: &String) -> usize {
+        text.len()
+    }
+
+    fn on_rename(&mut self, name: String, id: u32) {
+        self.name = format!("{}{}", name, id);
+    }
+
+    fn on_state(&self) -> (Vec<String>, String) {
+        (self.lines.clone(), self.name.clone())
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Log: ChatHandler
+    }
+}
+
+#[test]
+fn non_copy_arguments() {
+    let mut system = System::new();
+    let a = system.add(Box::new(Log::default()));
+    let b = system.add(Box::new(Log::default()));
+
+    system.message(String::from("hello"), vec![String::from("greeting")]);
+    assert_eq!(system.length(String::from("four")), 8);
+    system.rename(String::from("log"), 1);
+    assert!(system.rename_to(b, String::from("other"), 2));
+
+    system.queue_message(String::from("queued"), Vec::new());
+    system.process_events();
+
+    assert!(system.message_to(a, String::from("direct"), Vec::new()));
+    assert_eq!(system.length_to(a, String::from("abc")), Some(3));
+
+    let (lines, name) = system.state_to(a).unwrap();
+    assert_eq!(lines, vec!["hello [\"greeting\"]", "queued []", "direct []"]);
+    assert_eq!(name, "log1");
+
+    let (lines, name) = system.state_to(b).unwrap();
+    assert_eq!(lines, vec!["hello [\"greeting\"]", "queued []"]);
+    assert_eq!(name, "other2");
+}
@@ -0,0 +1,59 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        PingHandler {
+            ping() -> &'static str => on_ping
+        }
+    }
+}
+
+struct Named(&'static str);
+
+impl PingHandler for Named {
+    fn on_ping(&mut self) -> &'static str {
+        self.0
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Named: PingHandler
+    }
+}
+
+#[test]
+fn equal_priorities_keep_insertion_order() {
+    let mut system = System::new();
+    system.add(Box::new(Named("a")));
+    system.add_with_priority(Box::new(Named("b")), 0);
+    system.add(Box::new(Named("c")));
+
+    assert_eq!(system.ping(), vec!["a", "b", "c"]);
+}
+
+#[test]
+fn higher_priorities_first() {
+    let mut system = System::new();
+    system.add(Box::new(Named("a")));
+    system.add_with_priority(Box::new(Named("b")), 5);
+    system.add_with_priority(Box::new(Named("c")), -1);
+    system.add_with_priority(Box::new(Named("d")), 5);
+
+    assert_eq!(system.ping(), vec!["b", "d", "a", "c"]);
+}
+
+#[test]
+fn order_after_slot_reuse() {
+    let mut system = System::new();
+    let a = system.add(Box::new(Named("a")));
+    system.add_with_priority(Box::new(Named("b")), 5);
+
+    // `c` reuses the slot `a` was removed from
+    system.remove(a);
+    system.add_with_priority(Box::new(Named("c")), 10);
+    assert_eq!(system.ping(), vec!["c", "b"]);
+
+    system.add_with_priority(Box::new(Named("d")), 7);
+    assert_eq!(system.ping(), vec!["c", "d", "b"]);
+}
@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        KeyHandler {
+            key(code: u32) => on_key [propagate]
+        }
+    }
+}
+
+struct Consumer {
+    id: u32,
+    code: u32,
+    calls: Rc<RefCell<Vec<u32>>>
+}
+
+impl KeyHandler for Consumer {
+    fn on_key(&mut self, code: u32) -> SystemPropagation {
+        self.calls.borrow_mut().push(self.id);
+
+        if code == self.code {
+            SystemPropagation::Stop
+        } else {
+            SystemPropagation::Continue
+        }
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Consumer: KeyHandler
+    }
+}
+
+#[test]
+fn stop_halts_delivery() {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let mut system = System::new();
+
+    for (id, code) in [(0, 10), (1, 11), (2, 12)] {
+        system.add(Box::new(Consumer { id, code, calls: calls.clone() }));
+    }
+
+    // Handlers after the one that stopped the signal are not called
+    assert!(system.key(11));
+    assert_eq!(*calls.borrow(), vec![0, 1]);
+
+    calls.borrow_mut().clear();
+    assert!(!system.key(13));
+    assert_eq!(*calls.borrow(), vec![0, 1, 2]);
+
+    calls.borrow_mut().clear();
+    assert!(system.key(10));
+    assert_eq!(*calls.borrow(), vec![0]);
+}
@@ -0,0 +1,76 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        StepHandler {
+            queue step(n: u32) => on_step;
+            note(n: u32) => on_note;
+            log() -> Vec<String> => on_log [last]
+        }
+    }
+}
+
+#[derive(Default)]
+struct Stepper {
+    log: Vec<String>
+}
+
+impl StepHandler for Stepper {
+    fn on_step(&mut self, queue: &mut SystemQueue, n: u32) {
+        self.log.push(format!("step {}", n));
+
+        if n > 0 {
+            queue.queue_step(n - 1);
+            queue.queue_note(n);
+        }
+    }
+
+    fn on_note(&mut self, n: u32) {
+        self.log.push(format!("note {}", n));
+    }
+
+    fn on_log(&mut self) -> Vec<String> {
+        self.log.clone()
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Stepper: StepHandler
+    }
+}
+
+#[test]
+fn events_processed_in_order() {
+    let mut system = System::new();
+    system.add(Box::new(Stepper::default()));
+
+    system.queue_step(2);
+    system.emit(SystemEvent::Note { n: 100 });
+    assert_eq!(system.log(), Some(Vec::new()));
+
+    // Events queued by slots are processed after those already in the queue
+    system.process_events();
+    let expected = ["step 2", "note 100", "step 1", "note 2", "step 0", "note 1"];
+    assert_eq!(system.log(), Some(expected.iter().map(|line| line.to_string()).collect()));
+
+    // Nothing is left in the queue
+    system.process_events();
+    assert_eq!(system.log().map(|log| log.len()), Some(expected.len()));
+}
+
+#[test]
+fn dispatch_calls_signal() {
+    let mut system = System::new();
+    system.add(Box::new(Stepper::default()));
+
+    // Dispatched events are delivered immediately, without going through the queue
+    system.dispatch(SystemEvent::Note { n: 3 });
+    assert_eq!(system.log(), Some(vec![String::from("note 3")]));
+
+    system.dispatch(SystemEvent::Step { n: 1 });
+    assert_eq!(system.log().map(|log| log.len()), Some(2));
+
+    system.process_events();
+    assert_eq!(system.log(), Some(["note 3", "step 1", "step 0", "note 1"].iter().map(|line| line.to_string()).collect()));
+}
@@ -0,0 +1,106 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+pub trait Identified {
+    fn id(&self) -> u64;
+}
+
+handlers_define_system! {
+    System {
+        * : Identified;
+
+        CountHandler {
+            count() -> u64 => on_count [sum]
+        }
+    }
+}
+
+struct Object {
+    id: u64
+}
+
+impl Identified for Object {
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl CountHandler for Object {
+    fn on_count(&mut self) -> u64 {
+        1
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Object: CountHandler
+    }
+}
+
+// xorshift64, so the sequence of operations is the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[test]
+fn random_add_remove_get() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut system = System::new();
+    let mut live: Vec<(SystemIndex, u64)> = Vec::new();
+    let mut removed: Vec<SystemIndex> = Vec::new();
+
+    for id in 0..10_000 {
+        match rng.below(3) {
+            0 | 1 if live.len() < 64 => {
+                let idx = system.add(Box::new(Object { id }));
+                live.push((idx, id));
+            },
+
+            _ if !live.is_empty() => {
+                let (idx, id) = live.swap_remove(rng.below(live.len()));
+                assert_eq!(system.remove(idx).map(|obj| obj.id()), Some(id));
+                removed.push(idx);
+            },
+
+            _ => ()
+        }
+
+        for &(idx, id) in live.iter() {
+            assert_eq!(system.get(idx).map(|obj| obj.id()), Some(id));
+            assert_eq!(system.get_mut(idx).map(|obj| obj.id()), Some(id));
+        }
+
+        if !removed.is_empty() {
+            let idx = removed[rng.below(removed.len())];
+            assert!(system.get(idx).is_none());
+            assert!(system.remove(idx).is_none());
+        }
+
+        assert_eq!(system.iter().count(), live.len());
+        assert_eq!(system.count(), live.len() as u64);
+    }
+}
+
+#[test]
+fn stale_index_after_reuse() {
+    let mut system = System::new();
+    let old = system.add(Box::new(Object { id: 1 }));
+    system.remove(old);
+
+    // The new object takes the removed object's slot, but not its index
+    let new = system.add(Box::new(Object { id: 2 }));
+    assert!(system.get(old).is_none());
+    assert!(system.remove(old).is_none());
+    assert_eq!(system.get(new).map(|obj| obj.id()), Some(2));
+    assert_eq!(system.count(), 1);
+}
@@ -0,0 +1,89 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        WidgetHandler {
+            draw() -> u32 => on_draw [sum];
+            count(&self) -> usize => on_count [sum]
+        }
+    }
+}
+
+struct Widget {
+    id: u32
+}
+
+impl WidgetHandler for Widget {
+    fn on_draw(&mut self) -> u32 {
+        self.id
+    }
+
+    fn on_count(&self) -> usize {
+        1
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Widget
+    }
+}
+
+#[test]
+fn dropped_guards_remove_objects() {
+    let mut system = System::new();
+    system.add(Box::new(Widget { id: 1 }));
+    let idx;
+
+    {
+        let guard = system.add_scoped(Box::new(Widget { id: 10 }));
+        assert!(system.get(guard.idx()).is_some());
+        assert_eq!(system.draw(), 11);
+        idx = guard.idx();
+    }
+
+    assert!(system.get(idx).is_none());
+    assert_eq!(system.count_to(idx), None);
+    assert_eq!(system.iter().count(), 1);
+
+    // Dropped objects no longer receive signals, even before the system is next used mutably
+    assert_eq!(system.count(), 1);
+    assert_eq!(system.draw(), 1);
+    assert_eq!(system.count(), 1);
+
+    let guard = system.add_scoped(Box::new(Widget { id: 100 }));
+    let idx = guard.idx();
+    drop(guard);
+    system.remove_dropped();
+    assert!(system.get(idx).is_none());
+    assert_eq!(system.iter().count(), 1);
+}
+
+#[test]
+fn guards_dropped_on_other_threads() {
+    let mut system = System::new();
+    let guard = system.add_scoped(Box::new(Widget { id: 1 }));
+    let kept = system.add(Box::new(Widget { id: 2 }));
+    let idx = guard.idx();
+
+    std::thread::spawn(move || drop(guard)).join().unwrap();
+
+    assert!(system.get(idx).is_none());
+    assert!(system.get(kept).is_some());
+    assert_eq!(system.iter().count(), 1);
+    assert_eq!(system.draw(), 2);
+    assert_eq!(system.iter().count(), 1);
+}
+
+#[test]
+fn released_guards_keep_objects() {
+    let mut system = System::new();
+    let idx = system.add_scoped(Box::new(Widget { id: 5 })).release();
+    assert_eq!(system.draw(), 5);
+    assert!(system.remove(idx).is_some());
+
+    // Guards outliving their system do nothing
+    let guard = system.add_scoped(Box::new(Widget { id: 6 }));
+    drop(system);
+    drop(guard);
+}
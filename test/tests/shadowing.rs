@@ -0,0 +1,81 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use handlers::{handlers_define_system, handlers_impl_object};
+
+// Arguments share their names with locals of the generated signals
+handlers_define_system! {
+    System {
+        ReportHandler {
+            report(results: u32) -> u32 => on_report [sum];
+            select(idx: usize) -> usize => on_select;
+            peek(&self, idx: usize, result: usize) -> usize => on_peek [last];
+            mark(target: u32, slot: u32) -> u32 => on_mark;
+            par step(chunk: usize, handlers: usize, positions: usize) -> usize => on_step [sum];
+            async fetch(futures: u32, outputs: u32) -> u32 => on_fetch [sum]
+        }
+    }
+}
+
+struct Reporter;
+
+impl ReportHandler for Reporter {
+    fn on_report(&mut self, results: u32) -> u32 {
+        results
+    }
+
+    fn on_select(&mut self, idx: usize) -> usize {
+        idx
+    }
+
+    fn on_peek(&self, idx: usize, result: usize) -> usize {
+        idx + result
+    }
+
+    fn on_mark(&mut self, target: u32, slot: u32) -> u32 {
+        target * slot
+    }
+
+    fn on_step(&mut self, chunk: usize, handlers: usize, positions: usize) -> usize {
+        chunk + handlers + positions
+    }
+
+    fn on_fetch(&mut self, futures: u32, outputs: u32) -> Pin<Box<dyn Future<Output = u32> + '_>> {
+        Box::pin(async move { futures + outputs })
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Reporter
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let mut context = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn arguments_named_like_locals() {
+    let mut system = System::new();
+    let a = system.add(Box::new(Reporter));
+    system.add(Box::new(Reporter));
+    system.connect_report(|results| results + 1);
+
+    assert_eq!(system.report(100), 301);
+    assert_eq!(system.select(7), vec![7, 7]);
+    assert_eq!(system.peek(3, 4), Some(7));
+    assert_eq!(system.mark(2, 5), vec![10, 10]);
+    assert_eq!(system.mark_to(a, 3, 4), Some(12));
+    assert_eq!(system.step(1, 2, 3), 12);
+    assert_eq!(block_on(system.fetch(1, 2)), 6);
+    assert_eq!(block_on(system.fetch_concurrent(2, 3)), 10);
+}
@@ -0,0 +1,73 @@
+use std::thread;
+
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System [Send + Sync] {
+        CountHandler {
+            increment(n: u64) => on_increment;
+            total(&self) -> u64 => on_total [sum]
+        }
+    }
+}
+
+struct Counter {
+    n: u64
+}
+
+impl CountHandler for Counter {
+    fn on_increment(&mut self, n: u64) {
+        self.n += n;
+    }
+
+    fn on_total(&self) -> u64 {
+        self.n
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Counter
+    }
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn shared_between_threads() {
+    assert_send_sync::<System>();
+
+    let shared = SystemShared::new(System::new());
+    shared.add(Box::new(Counter { n: 0 }));
+    shared.add(Box::new(Counter { n: 0 }));
+
+    let workers: Vec<_> = (0..4).map(|_| {
+        let shared = shared.clone();
+
+        thread::spawn(move || {
+            for _ in 0..100 {
+                shared.increment(1);
+                shared.queue_increment(1);
+            }
+        })
+    }).collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    shared.process_events();
+    assert_eq!(shared.total(), 1600);
+    assert_eq!(shared.lock().iter().count(), 2);
+}
+
+#[test]
+fn shared_scoped_objects() {
+    let shared = SystemShared::new(System::new());
+    let guard = shared.add_scoped(Box::new(Counter { n: 3 }));
+    assert_eq!(shared.total(), 3);
+
+    thread::spawn(move || drop(guard)).join().unwrap();
+    shared.increment(1);
+    assert_eq!(shared.total(), 0);
+}
@@ -0,0 +1,79 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        PokeHandler {
+            poke(n: u32) => on_poke;
+            total() -> u32 => on_total [sum]
+        }
+
+        NameHandler {
+            name() -> &'static str => on_name
+        }
+    }
+}
+
+struct Target {
+    pokes: u32
+}
+
+impl PokeHandler for Target {
+    fn on_poke(&mut self, n: u32) {
+        self.pokes += n;
+    }
+
+    fn on_total(&mut self) -> u32 {
+        self.pokes
+    }
+}
+
+struct Named;
+
+impl NameHandler for Named {
+    fn on_name(&mut self) -> &'static str {
+        "named"
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Target: PokeHandler
+    }
+}
+
+handlers_impl_object! {
+    System {
+        Named: NameHandler
+    }
+}
+
+#[test]
+fn only_target_receives() {
+    let mut system = System::new();
+    let a = system.add(Box::new(Target { pokes: 0 }));
+    let b = system.add(Box::new(Target { pokes: 0 }));
+    let named = system.add(Box::new(Named));
+
+    assert!(system.poke_to(b, 5));
+    assert_eq!(system.total_to(a), Some(0));
+    assert_eq!(system.total_to(b), Some(5));
+    assert_eq!(system.total(), 5);
+
+    // Objects which do not implement the handler do not receive the signal
+    assert!(!system.poke_to(named, 1));
+    assert_eq!(system.total_to(named), None);
+    assert_eq!(system.name_to(named), Some("named"));
+    assert_eq!(system.name_to(a), None);
+}
+
+#[test]
+fn stale_target() {
+    let mut system = System::new();
+    let a = system.add(Box::new(Target { pokes: 0 }));
+    system.remove(a);
+    let b = system.add(Box::new(Target { pokes: 0 }));
+
+    assert!(!system.poke_to(a, 1));
+    assert_eq!(system.total_to(a), None);
+    assert_eq!(system.total_to(b), Some(0));
+}
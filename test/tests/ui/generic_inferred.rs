@@ -0,0 +1,28 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+pub struct Gl;
+
+handlers_define_system! {
+    System<R> {
+        DrawHandler {
+            draw(renderer: &R) -> u32 => on_draw [sum]
+        }
+    }
+}
+
+struct Sprite;
+
+impl DrawHandler<Gl> for Sprite {
+    fn on_draw(&mut self, _: &Gl) -> u32 {
+        1
+    }
+}
+
+// Only implemented for `System<Gl>`, which could not be found for every `System<R>`
+handlers_impl_object! {
+    System {
+        Sprite
+    }
+}
+
+fn main() {}
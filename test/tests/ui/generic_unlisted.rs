@@ -0,0 +1,34 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System<R> {
+        DrawHandler {
+            draw(renderer: &R) -> u32 => on_draw [sum]
+        }
+
+        ResizeHandler {
+            resize(width: u32) => on_resize
+        }
+    }
+}
+
+struct Sprite;
+
+impl<R> DrawHandler<R> for Sprite {
+    fn on_draw(&mut self, _: &R) -> u32 {
+        1
+    }
+}
+
+impl ResizeHandler<u32> for Sprite {
+    fn on_resize(&mut self, _: u32) {}
+}
+
+// ResizeHandler is only implemented for `System<u32>`, so it cannot be left to inference
+handlers_impl_object! {
+    System {
+        Sprite: DrawHandler
+    }
+}
+
+fn main() {}
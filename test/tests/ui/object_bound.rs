@@ -0,0 +1,12 @@
+use handlers::handlers_define_system;
+
+// Only auto traits can be added to the boxed objects
+handlers_define_system! {
+    System [Send + std::fmt::Debug] {
+        TickHandler {
+            tick() => on_tick
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+use handlers::handlers_impl_object;
+
+mod gfx {
+    use handlers::handlers_define_system;
+
+    pub trait Renderer {}
+
+    handlers_define_system! {
+        pub Scene<R: Renderer> {
+            DrawHandler {
+                draw(renderer: &R) => on_draw
+            }
+        }
+    }
+}
+
+struct Sprite;
+
+impl<R: gfx::Renderer> gfx::DrawHandler<R> for Sprite {
+    fn on_draw(&mut self, _: &R) {}
+}
+
+// The bound is only in scope where the system is defined
+handlers_impl_object! {
+    gfx::Scene {
+        Sprite: DrawHandler
+    }
+}
+
+fn main() {}
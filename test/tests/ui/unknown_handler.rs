@@ -0,0 +1,23 @@
+use handlers::{handlers_define_system, handlers_impl_object};
+
+handlers_define_system! {
+    System {
+        MouseHandler {
+            click() => on_click
+        }
+    }
+}
+
+struct Button;
+
+impl MouseHandler for Button {
+    fn on_click(&mut self) {}
+}
+
+handlers_impl_object! {
+    System {
+        Button: MosueHandler
+    }
+}
+
+fn main() {}
@@ -0,0 +1,97 @@
+mod events {
+    use handlers::{handlers_define_system, handlers_impl_object};
+
+    handlers_define_system! {
+        pub(crate) System {
+            InputHandler {
+                input(c: char) -> char => on_input
+            }
+
+            pub(super) CountHandler {
+                count() -> u32 => on_count [sum]
+            }
+        }
+    }
+
+    pub(crate) struct Echo;
+
+    impl InputHandler for Echo {
+        fn on_input(&mut self, c: char) -> char {
+            c
+        }
+    }
+
+    impl CountHandler for Echo {
+        fn on_count(&mut self) -> u32 {
+            1
+        }
+    }
+
+    handlers_impl_object! {
+        System {
+            Echo: InputHandler, CountHandler
+        }
+    }
+}
+
+use events::{Echo, System, SystemIndex};
+
+#[test]
+fn restricted_visibility() {
+    let mut system = System::new();
+    let idx: SystemIndex = system.add(Box::new(Echo));
+
+    assert_eq!(system.input('x'), vec!['x']);
+    assert_eq!(system.count(), 1);
+    assert!(system.remove(idx).is_some());
+}
+
+pub mod library {
+    use handlers::{handlers_define_system, handlers_impl_object};
+
+    handlers_define_system! {
+        pub Library {
+            pub BookHandler {
+                read() -> u32 => on_read [sum]
+            }
+
+            // Kept private to this module, even though the system is public
+            pub(self) AuditHandler {
+                audit() -> u32 => on_audit [sum]
+            }
+        }
+    }
+
+    pub struct Book;
+
+    impl BookHandler for Book {
+        fn on_read(&mut self) -> u32 {
+            1
+        }
+    }
+
+    impl AuditHandler for Book {
+        fn on_audit(&mut self) -> u32 {
+            2
+        }
+    }
+
+    handlers_impl_object! {
+        Library {
+            Book: BookHandler, AuditHandler
+        }
+    }
+
+    pub fn audit(library: &mut Library) -> u32 {
+        library.audit()
+    }
+}
+
+#[test]
+fn private_handler_in_public_system() {
+    let mut system = library::Library::new();
+    system.add(Box::new(library::Book));
+
+    assert_eq!(system.read(), 1);
+    assert_eq!(library::audit(&mut system), 2);
+}